@@ -0,0 +1,159 @@
+//! I2C address of the ADS122C04
+//!
+//! The address is selected by connecting each of the pins A0 and A1 to DGND, DVDD, SDA or
+//! SCL, giving 16 addresses from 0x40 to 0x4F.
+
+use embedded_hal::i2c;
+
+use crate::registers::*;
+
+/// Connection of an address pin (A0 or A1)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum AddressPin {
+    /// tied to DGND
+    Dgnd = 0b00,
+    /// tied to DVDD
+    Dvdd = 0b01,
+    /// tied to SDA
+    Sda = 0b10,
+    /// tied to SCL
+    Scl = 0b11,
+}
+
+impl AddressPin {
+    fn from_bits(val: u8) -> Self {
+        match val & 0b11 {
+            0b00 => AddressPin::Dgnd,
+            0b01 => AddressPin::Dvdd,
+            0b10 => AddressPin::Sda,
+            _ => AddressPin::Scl,
+        }
+    }
+}
+
+/// The value is not an ADS122C04 I2C address
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct InvalidAddress(pub u8);
+
+/// I2C address of an ADS122C04
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Address(u8);
+
+impl Address {
+    /// Lowest address, with A0 and A1 tied to DGND
+    const BASE: u8 = 0x40;
+
+    /// Address selected by the connections of A1 and A0
+    pub const fn new(a1: AddressPin, a0: AddressPin) -> Self {
+        Address(Self::BASE | ((a1 as u8) << 2) | a0 as u8)
+    }
+
+    /// Connection of A1
+    pub fn a1(&self) -> AddressPin {
+        AddressPin::from_bits(self.0 >> 2)
+    }
+
+    /// Connection of A0
+    pub fn a0(&self) -> AddressPin {
+        AddressPin::from_bits(self.0)
+    }
+
+    /// All 16 addresses of the ADS122C04 in ascending order
+    pub fn all() -> impl Iterator<Item = Address> {
+        (Self::BASE..Self::BASE + 16).map(Address)
+    }
+}
+
+impl Default for Address {
+    /// A0 and A1 tied to DGND
+    fn default() -> Self {
+        Address(Self::BASE)
+    }
+}
+
+impl TryFrom<u8> for Address {
+    type Error = InvalidAddress;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        match val {
+            0x40..=0x4F => Ok(Address(val)),
+            _ => Err(InvalidAddress(val)),
+        }
+    }
+}
+
+impl From<Address> for u8 {
+    fn from(address: Address) -> Self {
+        address.0
+    }
+}
+
+/// Find all ADS122C04 devices on the bus by reading config register 0 at every address.
+/// Addresses that do not respond or answer with a value that is not a valid config register 0
+/// are skipped. Other devices in the 0x40..0x4F range that happen to answer the read with a
+/// valid value are reported as well.
+pub fn scan<I2C: i2c::I2c>(i2c: &mut I2C) -> impl Iterator<Item = Address> + '_ {
+    Address::all().filter(move |address| {
+        let mut buffer = [0];
+        i2c.write_read(address.0, &[Commands::RReg as u8], &mut buffer)
+            .is_ok()
+            && Config0::from_bits(buffer[0]).is_ok()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PINS: [AddressPin; 4] = [
+        AddressPin::Dgnd,
+        AddressPin::Dvdd,
+        AddressPin::Sda,
+        AddressPin::Scl,
+    ];
+
+    #[test]
+    fn strapping() {
+        assert_eq!(
+            u8::from(Address::new(AddressPin::Dgnd, AddressPin::Dgnd)),
+            0x40
+        );
+        assert_eq!(
+            u8::from(Address::new(AddressPin::Dgnd, AddressPin::Sda)),
+            0x42
+        );
+        assert_eq!(
+            u8::from(Address::new(AddressPin::Dvdd, AddressPin::Dgnd)),
+            0x44
+        );
+        assert_eq!(
+            u8::from(Address::new(AddressPin::Scl, AddressPin::Scl)),
+            0x4F
+        );
+        assert_eq!(
+            Address::default(),
+            Address::new(AddressPin::Dgnd, AddressPin::Dgnd)
+        );
+        for a1 in PINS {
+            for a0 in PINS {
+                let address = Address::new(a1, a0);
+                assert_eq!(address.a1(), a1);
+                assert_eq!(address.a0(), a0);
+            }
+        }
+    }
+
+    #[test]
+    fn all_addresses() {
+        let mut count = 0;
+        for (address, val) in Address::all().zip(0x40..) {
+            assert_eq!(u8::from(address), val);
+            assert_eq!(Address::try_from(val), Ok(address));
+            count += 1;
+        }
+        assert_eq!(count, 16);
+        for val in [0x00, 0x3F, 0x50, 0xFF] {
+            assert_eq!(Address::try_from(val), Err(InvalidAddress(val)));
+        }
+    }
+}
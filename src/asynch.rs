@@ -0,0 +1,664 @@
+//! Async I2C/UART interfaces and driver, available with the `async` feature
+
+use embedded_hal_async::{digital::Wait, i2c};
+use embedded_io_async::{Read, ReadExactError, Write};
+
+use crate::address::Address;
+use crate::calibration::{is_valid_factor, CalibrationKey};
+use crate::config::{Config, ShadowRegisters};
+use crate::interface::{
+    check_integrity, decode_data, integrity_len, I2cInterface, Interface, NoDrdy, SerialInterface,
+    Variant,
+};
+use crate::registers::*;
+use crate::{raw_to_voltage, ADS122x04, Error, Sample, DRDY_BIT_POLLS};
+
+/// Wraps an interface to drive the device through the async bus traits
+#[derive(Debug)]
+pub struct Async<BUS>(pub(crate) BUS);
+
+impl Wait for NoDrdy {
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        match *self {}
+    }
+
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        match *self {}
+    }
+}
+
+/// Write data asynchronously
+#[allow(async_fn_in_trait)]
+pub trait AsyncWriteData: Interface {
+    /// Error type
+    type Error;
+    /// Write to an u8 register
+    async fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error>;
+    /// Write data. The first element corresponds to the starting address.
+    async fn write_data(&mut self, payload: u8) -> Result<(), Self::Error>;
+}
+
+impl<I2C, E> AsyncWriteData for I2cInterface<I2C>
+where
+    I2C: i2c::I2c<Error = E>,
+{
+    type Error = Error<E>;
+    async fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error> {
+        let register = Commands::WReg as u8 | (register << 2); // write command
+        self.i2c
+            .write(self.address, &[register, data])
+            .await
+            .map_err(Error::CommError)
+    }
+
+    async fn write_data(&mut self, payload: u8) -> Result<(), Self::Error> {
+        self.i2c
+            .write(self.address, &[payload])
+            .await
+            .map_err(Error::CommError)
+    }
+}
+
+impl<UART, E> AsyncWriteData for SerialInterface<UART>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+{
+    type Error = Error<E>;
+    async fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error> {
+        let register = Commands::WReg as u8 | (register << 1); // write command (rrrx)
+        self.serial
+            .write_all(&[0x55, register, data])
+            .await
+            .map_err(Error::CommError)?;
+        self.serial.flush().await.map_err(Error::CommError)
+    }
+
+    async fn write_data(&mut self, payload: u8) -> Result<(), Self::Error> {
+        self.serial
+            .write_all(&[0x55, payload])
+            .await
+            .map_err(Error::CommError)?;
+        self.serial.flush().await.map_err(Error::CommError)
+    }
+}
+
+/// Read data asynchronously
+#[allow(async_fn_in_trait)]
+pub trait AsyncReadData: Interface {
+    /// Error type
+    type Error;
+    /// Read an u8 register, followed by its integrity bytes if `crc` is enabled
+    async fn read_register(&mut self, register: u8, crc: Crc) -> Result<u8, Self::Error>;
+    /// Read the conversion data, preceded by the conversion counter if `data_counter` is
+    /// enabled and followed by its integrity bytes if `crc` is enabled
+    async fn read_data(
+        &mut self,
+        crc: Crc,
+        data_counter: bool,
+    ) -> Result<(u32, Option<u8>), Self::Error>;
+}
+
+impl<I2C, E> AsyncReadData for I2cInterface<I2C>
+where
+    I2C: i2c::I2c<Error = E>,
+{
+    type Error = Error<E>;
+    async fn read_register(&mut self, register: u8, crc: Crc) -> Result<u8, Self::Error> {
+        let register = Commands::RReg as u8 | (register << 2); // read command
+        let mut buffer = [0; 3];
+        let len = 1 + integrity_len(crc, 1);
+        self.i2c
+            .write_read(self.address, &[register], &mut buffer[..len])
+            .await
+            .map_err(Error::CommError)?;
+        check_integrity(&buffer[..len], 1, crc)?;
+        Ok(buffer[0])
+    }
+
+    async fn read_data(
+        &mut self,
+        crc: Crc,
+        data_counter: bool,
+    ) -> Result<(u32, Option<u8>), Self::Error> {
+        let mut buffer = [0; 8];
+        let data_len = 3 + data_counter as usize;
+        let len = data_len + integrity_len(crc, data_len);
+        self.i2c
+            .write_read(self.address, &[Commands::RData as u8], &mut buffer[..len])
+            .await
+            .map_err(Error::CommError)?;
+        check_integrity(&buffer[..len], data_len, crc)?;
+        Ok(decode_data(&buffer, data_counter))
+    }
+}
+
+impl<UART, E> AsyncReadData for SerialInterface<UART>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+{
+    type Error = Error<E>;
+    async fn read_register(&mut self, register: u8, crc: Crc) -> Result<u8, Self::Error> {
+        let register = Commands::RReg as u8 | (register << 1); // read command (rrrx)
+        self.serial
+            .write_all(&[0x55, register])
+            .await
+            .map_err(Error::CommError)?;
+        self.serial.flush().await.map_err(Error::CommError)?;
+
+        let mut out = [0; 3];
+        let len = 1 + integrity_len(crc, 1);
+        read_exact(&mut self.serial, &mut out[..len]).await?;
+        check_integrity(&out[..len], 1, crc)?;
+
+        Ok(out[0])
+    }
+
+    async fn read_data(
+        &mut self,
+        crc: Crc,
+        data_counter: bool,
+    ) -> Result<(u32, Option<u8>), Self::Error> {
+        let mut out = [0; 8];
+        let data_len = 3 + data_counter as usize;
+        let len = data_len + integrity_len(crc, data_len);
+        self.serial
+            .write_all(&[0x55, Commands::RData as u8])
+            .await
+            .map_err(Error::CommError)?;
+        self.serial.flush().await.map_err(Error::CommError)?;
+        read_exact(&mut self.serial, &mut out[..len]).await?;
+        check_integrity(&out[..len], data_len, crc)?;
+        Ok(decode_data(&out, data_counter))
+    }
+}
+
+/// Fill the buffer, the response may arrive in several chunks
+async fn read_exact<UART, E>(serial: &mut UART, buffer: &mut [u8]) -> Result<(), Error<E>>
+where
+    UART: Read<Error = E>,
+{
+    serial.read_exact(buffer).await.map_err(|e| match e {
+        ReadExactError::UnexpectedEof => Error::Timeout,
+        ReadExactError::Other(e) => Error::CommError(e),
+    })
+}
+
+impl<I2C, E> ADS122x04<Async<I2cInterface<I2C>>>
+where
+    I2C: i2c::I2c<Error = E>,
+{
+    /// Create a new async ADS122C04 device by supplying an I2C address and I2C handler
+    pub fn new_i2c_async(address: u8, i2c: I2C) -> Self {
+        Self::with_bus(Async(I2cInterface { i2c, address }))
+    }
+
+    /// Create a new async ADS122C04 device at the address selected by the A1/A0 strapping
+    pub fn new_i2c_async_with_address(address: Address, i2c: I2C) -> Self {
+        Self::new_i2c_async(address.into(), i2c)
+    }
+}
+
+impl<UART, E> ADS122x04<Async<SerialInterface<UART>>>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+{
+    /// Create a new async ADS122U04 device by supplying a serial handler (UART)
+    pub fn new_serial_async(serial: UART) -> Self {
+        Self::with_bus(Async(SerialInterface { serial }))
+    }
+}
+
+impl<BUS, DRDY, E> ADS122x04<Async<BUS>, DRDY>
+where
+    BUS: AsyncReadData<Error = Error<E>> + AsyncWriteData<Error = Error<E>>,
+    DRDY: Wait,
+{
+    /// updates a specified config register
+    async fn update_reg(&mut self, reg: u8) -> Result<(), Error<E>> {
+        let val = self.encode_reg(reg)?;
+        self.shadow.mark_dirty(reg);
+        self.bus.0.write_register(reg, val).await?;
+        if self.verify_writes {
+            let actual = self.read_reg(reg).await?;
+            self.check_written(reg, val, actual)?;
+        }
+        self.shadow.mark_clean(reg);
+        Ok(())
+    }
+
+    /// Write all dirty shadow registers to the device
+    pub async fn flush(&mut self) -> Result<(), Error<E>> {
+        // config register 2 goes first, it selects the CRC mode used to read back the others
+        for reg in [
+            Config2::ADDRESS,
+            Config0::ADDRESS,
+            Config1::ADDRESS,
+            Config3::ADDRESS,
+            Config4::ADDRESS,
+        ] {
+            if self.shadow.is_dirty(reg) {
+                self.update_reg(reg).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read all config registers from the device into the shadow registers, discarding
+    /// unwritten changes. The CRC mode is taken from the device, so that this also recovers
+    /// from a reset of the device, e.g. a brown-out, while CRC was enabled.
+    pub async fn resync(&mut self) -> Result<(), Error<E>> {
+        // a device that has been reset answers without the integrity bytes of the CRC mode
+        let config2 = match self.read_reg(Config2::ADDRESS).await {
+            Err(Error::CrcMismatch | Error::Timeout) => {
+                self.bus
+                    .0
+                    .read_register(Config2::ADDRESS, Crc::Disabled)
+                    .await?
+            }
+            result => result?,
+        };
+        self.shadow.config.crc = Config2::from_bits(config2)?.crc;
+        let config = self.read_config().await?;
+        if config.data_counter_enable != self.shadow.config.data_counter_enable {
+            self.last_counter = None;
+        }
+        self.shadow = ShadowRegisters::new(config);
+        if <BUS::Variant as Variant>::LAST_REGISTER >= Config4::ADDRESS {
+            self.gpio = Config4::from_bits(self.read_reg(Config4::ADDRESS).await?);
+        }
+        Ok(())
+    }
+
+    /// Rewrite all shadow registers to the device, e.g. after a brown-out reset it
+    pub async fn restore(&mut self) -> Result<(), Error<E>> {
+        for reg in Config::ADDRESSES {
+            self.shadow.mark_dirty(reg);
+        }
+        if <BUS::Variant as Variant>::LAST_REGISTER >= Config4::ADDRESS {
+            self.shadow.mark_dirty(Config4::ADDRESS);
+        }
+        self.flush().await
+    }
+
+    /// reads a specified config register
+    async fn read_reg(&mut self, reg: u8) -> Result<u8, Error<E>> {
+        if reg <= <BUS::Variant as Variant>::LAST_REGISTER {
+            self.bus.0.read_register(reg, self.shadow.config.crc).await
+        } else {
+            Err(Error::InvalidValue)
+        }
+    }
+
+    /// Apply a whole configuration, writing only the config registers that change
+    pub async fn apply_config(&mut self, config: &Config) -> Result<(), Error<E>> {
+        let previous = core::mem::replace(&mut self.shadow.config, *config);
+        if previous.data_counter_enable != config.data_counter_enable {
+            self.last_counter = None;
+        }
+        for reg in Config::ADDRESSES {
+            if previous.register(reg) != config.register(reg) {
+                self.shadow.mark_dirty(reg);
+            }
+        }
+        self.flush().await
+    }
+
+    /// Read and decode all config registers from the device. The voltage of an external or
+    /// analog supply reference cannot be read back and is taken from the cached configuration.
+    pub async fn read_config(&mut self) -> Result<Config, Error<E>> {
+        let mut registers = [0; 4];
+        for (reg, val) in Config::ADDRESSES.into_iter().zip(registers.iter_mut()) {
+            *val = self.read_reg(reg).await?;
+        }
+        Ok(Config::from_registers(
+            registers,
+            self.shadow.config.v_ref.to_voltage(),
+        )?)
+    }
+
+    /// Average a number of single conversions of the raw ADC value with the offset subtracted
+    async fn average_raw_adc(&mut self, count: i32) -> Result<i32, Error<E>> {
+        let mut sum = 0;
+        for _ in 0..count {
+            self.start().await?;
+            self.wait_for_data().await?;
+            sum += self.get_raw_adc().await?;
+        }
+        Ok(sum / count)
+    }
+
+    /// Calibrate the offset (according to 8.3.11 Offset Calibration in datasheet) at the
+    /// current gain, PGA bypass and data rate. The result is stored in the calibration table
+    /// and subtracted whenever these settings are active again, while the fallback
+    /// [`offset`](ADS122x04::offset) for uncalibrated settings is left unchanged. This is
+    /// recommended upon startup for each used setting. Fails with
+    /// [`CalibrationError::TableFull`](crate::calibration::CalibrationError::TableFull) if the
+    /// setting has no offset yet and the table is full.
+    pub async fn calibrate_offset(&mut self) -> Result<(), Error<E>> {
+        const NUM_AVG: i32 = 10;
+        // short the inputs to mid-supply (AVDD + AVSS) / 2
+        let previous_mux = self.shadow.config.mux;
+        let previous_mode = self.shadow.config.conversion_mode;
+        self.set_input_mux(Mux::Shorted).await?;
+        self.set_conversion_mode(ConversionMode::SingleShot).await?;
+        // take multiple readings and average, adding back the offset subtracted from them
+        let key = CalibrationKey::from_config(&self.shadow.config);
+        let offset = self.active_offset() + self.average_raw_adc(NUM_AVG).await?;
+        // return to previous mux and conversion mode
+        self.set_input_mux(previous_mux).await?;
+        self.set_conversion_mode(previous_mode).await?;
+        // store offset
+        Ok(self.calibration.set_offset(key, offset)?)
+    }
+
+    /// Calibrate the gain error at the current gain by measuring a known voltage in V applied
+    /// to the selected input. The correction factor is stored for the current gain, applied
+    /// to all following voltages and returned. The offset should be calibrated first.
+    pub async fn calibrate_gain(&mut self, reference_voltage: f32) -> Result<f32, Error<E>> {
+        const NUM_AVG: i32 = 10;
+        let raw = self.average_raw_adc(NUM_AVG).await?;
+        let gain = self.shadow.config.gain;
+        let measured = raw_to_voltage(raw, self.shadow.config.v_ref, gain);
+        let factor = reference_voltage / measured;
+        if !is_valid_factor(factor) {
+            return Err(Error::InvalidValue);
+        }
+        self.gain_correction[gain as usize] = factor;
+        Ok(factor)
+    }
+
+    /// Two-point calibration: calibrate the offset with shorted inputs, then the gain with a
+    /// known voltage in V applied to the selected input. Returns the gain correction factor.
+    pub async fn calibrate_offset_and_gain(
+        &mut self,
+        reference_voltage: f32,
+    ) -> Result<f32, Error<E>> {
+        self.calibrate_offset().await?;
+        self.calibrate_gain(reference_voltage).await
+    }
+
+    /// Enable or disable the programmable gain amplifier (PGA)
+    pub async fn set_pga_bypass(&mut self, state: bool) -> Result<(), Error<E>> {
+        self.shadow.config.pga_bypass = state;
+        self.update_reg(Config0::ADDRESS).await
+    }
+
+    /// Read the status of the programmable gain amplifier (PGA)
+    pub async fn get_pga_bypass(&mut self) -> Result<bool, Error<E>> {
+        Ok(Config0::from_bits(self.read_reg(Config0::ADDRESS).await?)?.pga_bypass)
+    }
+
+    /// Set the gain as either 0, 1, 2, 4, 8, 16, 32, 64 or 128
+    pub async fn set_gain(&mut self, gain: Gain) -> Result<(), Error<E>> {
+        self.shadow.config.gain = gain;
+        self.update_reg(Config0::ADDRESS).await
+    }
+
+    /// Read the gain value
+    pub async fn get_gain(&mut self) -> Result<Gain, Error<E>> {
+        Ok(Config0::from_bits(self.read_reg(Config0::ADDRESS).await?)?.gain)
+    }
+
+    /// Set the input multiplexer (MUX)
+    pub async fn set_input_mux(&mut self, mux: Mux) -> Result<(), Error<E>> {
+        self.shadow.config.mux = mux;
+        self.update_reg(Config0::ADDRESS).await
+    }
+
+    /// Read the input multiplexer (MUX) setting
+    pub async fn get_input_mux(&mut self) -> Result<Mux, Error<E>> {
+        Ok(Config0::from_bits(self.read_reg(Config0::ADDRESS).await?)?.mux)
+    }
+
+    /// Enable or disable temperature sensor mode (TS)
+    pub async fn set_temperature_sensor_mode(&mut self, state: bool) -> Result<(), Error<E>> {
+        self.shadow.config.temperature_sensor_mode = state;
+        self.update_reg(Config1::ADDRESS).await
+    }
+
+    /// Read the temperature sensor mode (TS)
+    pub async fn get_temperature_sensor_mode(&mut self) -> Result<bool, Error<E>> {
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS).await?)?.temperature_sensor_mode)
+    }
+
+    /// Set the voltage reference (VREF)
+    pub async fn set_vref(&mut self, v_ref: VRef) -> Result<(), Error<E>> {
+        self.shadow.config.v_ref = v_ref;
+        self.update_reg(Config1::ADDRESS).await
+    }
+
+    /// Read the voltage reference (VREF)
+    pub async fn get_vref(&mut self) -> Result<VRef, Error<E>> {
+        let config = Config1::from_bits(self.read_reg(Config1::ADDRESS).await?)?;
+        Ok(config
+            .v_ref
+            .with_voltage(self.shadow.config.v_ref.to_voltage()))
+    }
+
+    /// Set the conversion mode (CM)
+    pub async fn set_conversion_mode(&mut self, mode: ConversionMode) -> Result<(), Error<E>> {
+        self.shadow.config.conversion_mode = mode;
+        self.update_reg(Config1::ADDRESS).await
+    }
+
+    /// Read the conversion mode (CM)
+    pub async fn get_conversion_mode(&mut self) -> Result<ConversionMode, Error<E>> {
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS).await?)?.conversion_mode)
+    }
+
+    /// Read the operating mode
+    pub async fn get_operating_mode(&mut self) -> Result<bool, Error<E>> {
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS).await?)?.turbo_mode())
+    }
+
+    /// Set the data rate
+    pub async fn set_data_rate(&mut self, rate: DataRate) -> Result<(), Error<E>> {
+        self.shadow.config.data_rate = rate;
+        self.update_reg(Config1::ADDRESS).await
+    }
+
+    /// Read the data rate
+    pub async fn get_data_rate(&mut self) -> Result<DataRate, Error<E>> {
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS).await?)?.data_rate)
+    }
+
+    /// Set the current level of the internal excitation current sources
+    pub async fn set_current_level(&mut self, current: CurrentSource) -> Result<(), Error<E>> {
+        self.shadow.config.current_source = current;
+        self.update_reg(Config2::ADDRESS).await
+    }
+
+    /// Read the current level of the internal excitation current sources
+    pub async fn get_current_level(&mut self) -> Result<CurrentSource, Error<E>> {
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS).await?)?.current_source)
+    }
+
+    /// Enable or disable the 10 uA burnout current sources
+    pub async fn set_burnout_current_source(&mut self, state: bool) -> Result<(), Error<E>> {
+        self.shadow.config.burn_out_current_sources = state;
+        self.update_reg(Config2::ADDRESS).await
+    }
+
+    /// Read the state of the 10 uA burnout current sources
+    pub async fn get_burnout_current_source(&mut self) -> Result<bool, Error<E>> {
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS).await?)?.burn_out_current_sources)
+    }
+
+    /// Set the CRC mode. Subsequent conversion and register reads are checked against the
+    /// integrity bytes sent by the device and fail with [`Error::CrcMismatch`] on corruption.
+    pub async fn set_crc(&mut self, crc: Crc) -> Result<(), Error<E>> {
+        self.shadow.config.crc = crc;
+        self.update_reg(Config2::ADDRESS).await
+    }
+
+    /// Read the CRC mode
+    pub async fn get_crc(&mut self) -> Result<Crc, Error<E>> {
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS).await?)?.crc)
+    }
+
+    /// Enable or disable data counter
+    pub async fn set_data_counter(&mut self, state: bool) -> Result<(), Error<E>> {
+        self.shadow.config.data_counter_enable = state;
+        self.last_counter = None;
+        self.update_reg(Config2::ADDRESS).await
+    }
+
+    /// Read the state of the data counter
+    pub async fn get_data_counter(&mut self) -> Result<bool, Error<E>> {
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS).await?)?.data_counter)
+    }
+
+    /// Read the data ready (DRDY) register
+    pub async fn get_data_ready(&mut self) -> Result<bool, Error<E>> {
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS).await?)?.data_ready)
+    }
+
+    /// Wait until a new conversion result is available. With a DRDY pin attached this waits
+    /// for the pin to go low without a limit, wrap the call in a timeout of the executor to
+    /// bound it. Otherwise the DRDY bit is polled over the bus until [`Error::Timeout`].
+    pub async fn wait_for_data(&mut self) -> Result<(), Error<E>> {
+        if let Some(drdy) = self.drdy.as_mut() {
+            return drdy.wait_for_low().await.map_err(|_| Error::PinError);
+        }
+        let mut timeout_counter = 0;
+        while !self.get_data_ready().await? {
+            timeout_counter += 1;
+            if timeout_counter > DRDY_BIT_POLLS {
+                return Err(Error::Timeout);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set the current routing of the excitation current source 1
+    pub async fn set_current_route_1(&mut self, route: CurrentRoute) -> Result<(), Error<E>> {
+        self.shadow.config.current_route_1 = route;
+        self.update_reg(Config3::ADDRESS).await
+    }
+
+    /// Read the current routing of the excitation current source 1
+    pub async fn get_current_route_1(&mut self) -> Result<CurrentRoute, Error<E>> {
+        Ok(Config3::from_bits(self.read_reg(Config3::ADDRESS).await?)?.current_route_1)
+    }
+
+    /// Set the current routing of the excitation current source 2
+    pub async fn set_current_route_2(&mut self, route: CurrentRoute) -> Result<(), Error<E>> {
+        self.shadow.config.current_route_2 = route;
+        self.update_reg(Config3::ADDRESS).await
+    }
+
+    /// Read the current routing of the excitation current source 2
+    pub async fn get_current_route_2(&mut self) -> Result<CurrentRoute, Error<E>> {
+        Ok(Config3::from_bits(self.read_reg(Config3::ADDRESS).await?)?.current_route_2)
+    }
+
+    /// Read a conversion result including the conversion counter if it is enabled.
+    /// In continuous mode, [`Sample::continuity`] reveals skipped or repeated conversions.
+    pub async fn read_sample(&mut self) -> Result<Sample, Error<E>> {
+        let frame = self
+            .bus
+            .0
+            .read_data(
+                self.shadow.config.crc,
+                self.shadow.config.data_counter_enable,
+            )
+            .await?;
+        Ok(self.record_sample(frame))
+    }
+
+    /// Read the raw ADC value and subtract the offset
+    pub async fn get_raw_adc(&mut self) -> Result<i32, Error<E>> {
+        self.read_sample().await.map(|sample| sample.raw)
+    }
+
+    /// Measure the internal temperature sensor in °C. Temperature sensor mode is enabled for
+    /// the measurement and the previous mode is restored afterwards, also if the measurement
+    /// fails.
+    pub async fn get_temperature_celsius(&mut self) -> Result<f32, Error<E>> {
+        let previous_mode = self.shadow.config.temperature_sensor_mode;
+        let result = self.read_temperature(previous_mode).await;
+        let restored = if previous_mode {
+            Ok(())
+        } else {
+            self.set_temperature_sensor_mode(false).await
+        };
+        let val = result?;
+        restored?;
+        Ok(self.raw_to_celsius(val))
+    }
+
+    /// Enable temperature sensor mode unless it is `enabled` already, take a conversion and
+    /// read its raw value
+    async fn read_temperature(&mut self, enabled: bool) -> Result<u32, Error<E>> {
+        if !enabled {
+            self.set_temperature_sensor_mode(true).await?;
+        }
+        self.start().await?;
+        self.wait_for_data().await?;
+        let (val, counter) = self
+            .bus
+            .0
+            .read_data(
+                self.shadow.config.crc,
+                self.shadow.config.data_counter_enable,
+            )
+            .await?;
+        self.last_counter = counter;
+        Ok(val)
+    }
+
+    /// Read the voltage of the ADC
+    pub async fn get_voltage(&mut self) -> Result<f32, Error<E>> {
+        // returns voltage in V
+        self.read_sample().await.map(|sample| sample.to_voltage())
+    }
+
+    /// Reset the device, the shadow registers return to the power-on defaults
+    pub async fn reset(&mut self) -> Result<(), Error<E>> {
+        self.last_counter = None;
+        self.pending_measurement = None;
+        self.bus.0.write_data(Commands::Reset as u8).await?;
+        self.shadow = ShadowRegisters::default();
+        self.gpio = Config4::default();
+        self.auto_read = false;
+        self.powered_down = false;
+        Ok(())
+    }
+
+    /// Start a measurement, this also wakes the device from power-down mode
+    pub async fn start(&mut self) -> Result<(), Error<E>> {
+        self.bus.0.write_data(Commands::StartSync as u8).await?;
+        self.powered_down = false;
+        Ok(())
+    }
+
+    /// Put the device in power-down mode, the configuration registers are retained.
+    /// The next call to [`start`](Self::start) wakes it up again.
+    pub async fn power_down(&mut self) -> Result<(), Error<E>> {
+        self.bus.0.write_data(Commands::PowerDown as u8).await?;
+        self.powered_down = true;
+        Ok(())
+    }
+
+    /// Wake the device with START/SYNC, wait for the conversion result and power down again.
+    /// Intended for duty-cycled sampling in single-shot conversion mode.
+    pub async fn sample_and_sleep(&mut self) -> Result<Sample, Error<E>> {
+        self.start().await?;
+        self.wait_for_data().await?;
+        let sample = self.read_sample().await?;
+        self.power_down().await?;
+        Ok(sample)
+    }
+}
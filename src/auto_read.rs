@@ -0,0 +1,90 @@
+//! Automatic data read mode of the ADS122U04
+//!
+//! In automatic data read mode (AUTO) the device sends each conversion result over the UART
+//! as soon as it is available, without an RDATA command. Together with continuous conversion
+//! mode this reaches the highest data rates, as long as each frame fits into the time between
+//! two conversions. A frame has 3 data bytes, 1 more with the conversion counter (DCNT) and 2
+//! more with CRC16, each byte taking 10 bit times on the UART. At 115200 baud, 2000 SPS in
+//! turbo mode leaves 5 bytes per frame, e.g. data and CRC16 without the counter; the full
+//! 6-byte frame needs at least 120000 baud.
+//!
+//! The frames are preceded by the conversion counter and followed by the integrity bytes as
+//! configured. With a CRC mode enabled, the stream realigns itself after lost bytes, and the
+//! conversion counter reveals dropped frames in [`Sample::continuity`].
+
+use embedded_hal::digital::InputPin;
+use embedded_io::{Read, Write};
+
+use crate::interface::WriteData;
+use crate::registers::*;
+use crate::{Error, Sample, ADS122U04};
+
+impl<UART, DRDY, E> ADS122U04<UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    DRDY: InputPin,
+{
+    /// Write config register 3 without reading it back, the response could be mixed up with
+    /// conversion data sent in automatic data read mode
+    fn write_auto_read(&mut self, state: bool) -> Result<(), Error<E>> {
+        self.auto_read = state;
+        self.shadow.mark_dirty(Config3::ADDRESS);
+        let val = self.encode_reg(Config3::ADDRESS)?;
+        self.bus.write_register(Config3::ADDRESS, val)?;
+        self.shadow.mark_clean(Config3::ADDRESS);
+        Ok(())
+    }
+
+    /// Switch to continuous conversion mode, enable automatic data read mode and start
+    /// converting. The returned stream yields the conversion results as they arrive.
+    pub fn start_auto_read(&mut self) -> Result<AutoRead<'_, UART, DRDY>, Error<E>> {
+        if let ConversionMode::SingleShot = self.shadow.config.conversion_mode {
+            self.set_conversion_mode(ConversionMode::Continuous)?;
+        }
+        self.write_auto_read(true)?;
+        self.last_counter = None;
+        self.start()?;
+        Ok(AutoRead { adc: self })
+    }
+}
+
+/// Conversion results streamed by the device in automatic data read mode, started with
+/// [`start_auto_read`](ADS122U04::start_auto_read). Dropping the stream does not stop the
+/// device from sending, call [`stop`](Self::stop) instead.
+pub struct AutoRead<'a, UART, DRDY> {
+    adc: &'a mut ADS122U04<UART, DRDY>,
+}
+
+impl<UART, DRDY, E> AutoRead<'_, UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    DRDY: InputPin,
+{
+    /// Wait for the next conversion result. A UART read timeout is reported as
+    /// [`Error::Timeout`] and a frame that cannot be realigned as [`Error::CrcMismatch`].
+    pub fn read_sample(&mut self) -> Result<Sample, Error<E>> {
+        let frame = self.adc.bus.read_auto_data(
+            self.adc.shadow.config.crc,
+            self.adc.shadow.config.data_counter_enable,
+        )?;
+        Ok(self.adc.record_sample(frame))
+    }
+
+    /// Disable automatic data read mode. The device keeps converting in continuous mode and
+    /// a result that was already being sent may still arrive on the UART.
+    pub fn stop(self) -> Result<(), Error<E>> {
+        self.adc.write_auto_read(false)
+    }
+}
+
+impl<UART, DRDY, E> Iterator for AutoRead<'_, UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    DRDY: InputPin,
+{
+    type Item = Result<Sample, Error<E>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.read_sample())
+    }
+}
@@ -0,0 +1,154 @@
+//! Load cell / Wheatstone bridge measurement
+//!
+//! The bridge is excited by the analog supply, which also serves as the voltage reference.
+//! The conversion result is then the bridge output relative to the excitation (mV/V),
+//! independent of the supply voltage.
+
+use embedded_hal::digital::InputPin;
+
+use crate::config::Config;
+use crate::interface::{ReadData, WriteData};
+use crate::registers::*;
+use crate::{ADS122x04, Error};
+
+/// Wheatstone bridge sensor, e.g. a load cell, connected to the ADC
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bridge {
+    /// differential inputs across the bridge output (MUX)
+    pub mux: Mux,
+    /// gain of the PGA (GAIN), typically 128
+    pub gain: Gain,
+    /// excitation voltage (AVDD - AVSS) in V, only used to convert results to voltages
+    pub excitation: f32,
+    /// engineering units per mV/V, e.g. the capacity divided by the rated output of a load cell
+    pub scale: f32,
+    /// raw ADC value of the unloaded bridge, subtracted before conversion, set by
+    /// [`tare`](Self::tare)
+    pub zero: i32,
+}
+
+impl Bridge {
+    /// Bridge on the given inputs with results in mV/V until a scale is set or calibrated
+    pub const fn new(mux: Mux, gain: Gain, excitation: f32) -> Self {
+        Bridge {
+            mux,
+            gain,
+            excitation,
+            scale: 1.0,
+            zero: 0,
+        }
+    }
+
+    /// Configuration measuring the bridge, keeping all other settings of `config`
+    pub fn config(&self, config: &Config) -> Config {
+        Config {
+            v_ref: VRef::AnalogSupply(self.excitation),
+            gain: self.gain,
+            mux: self.mux,
+            pga_bypass: false,
+            temperature_sensor_mode: false,
+            ..*config
+        }
+    }
+
+    /// Configure the device for measuring the bridge
+    pub fn configure<BUS, DRDY, E>(&self, adc: &mut ADS122x04<BUS, DRDY>) -> Result<(), Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let config = self.config(adc.config());
+        adc.apply_config(&config)
+    }
+
+    /// Bridge output in mV/V of a raw ADC value measured with the configuration of
+    /// [`config`](Self::config), relative to the [`zero`](Self::zero)
+    pub fn mv_per_v(&self, raw: i32) -> f32 {
+        (raw - self.zero) as f32 / (1 << 23) as f32 / self.gain.to_factor() * 1000.0
+    }
+
+    /// Convert a raw ADC value to engineering units
+    pub fn to_units(&self, raw: i32) -> f32 {
+        self.mv_per_v(raw) * self.scale
+    }
+
+    /// Configure the device and average the given number of raw ADC values
+    fn average<BUS, DRDY, E>(
+        &self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+        samples: u8,
+    ) -> Result<i32, Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        if samples == 0 {
+            return Err(Error::InvalidValue);
+        }
+        self.configure(adc)?;
+        let mut sum = 0i64;
+        for _ in 0..samples {
+            adc.start()?;
+            adc.wait_for_data()?;
+            sum += adc.get_raw_adc()? as i64;
+        }
+        Ok((sum / samples as i64) as i32)
+    }
+
+    /// Zero the bridge with no load applied. The average of the given number of conversions
+    /// is stored as the [`zero`](Self::zero) of the bridge, so that the unloaded bridge reads
+    /// zero. The offset calibration of the device is left alone.
+    pub fn tare<BUS, DRDY, E>(
+        &mut self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+        samples: u8,
+    ) -> Result<(), Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        self.zero = self.average(adc, samples)?;
+        Ok(())
+    }
+
+    /// Calibrate the [`scale`](Self::scale) with a known load applied, given in engineering
+    /// units. The bridge must have been tared before.
+    pub fn calibrate_span<BUS, DRDY, E>(
+        &mut self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+        load: f32,
+        samples: u8,
+    ) -> Result<(), Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let raw = self.average(adc, samples)?;
+        if raw == self.zero {
+            return Err(Error::InvalidValue);
+        }
+        self.scale = load / self.mv_per_v(raw);
+        Ok(())
+    }
+
+    /// Configure the device and measure the bridge output in mV/V
+    pub fn read_mv_per_v<BUS, DRDY, E>(
+        &self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+    ) -> Result<f32, Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        self.average(adc, 1).map(|raw| self.mv_per_v(raw))
+    }
+
+    /// Configure the device and measure the load in engineering units
+    pub fn read<BUS, DRDY, E>(&self, adc: &mut ADS122x04<BUS, DRDY>) -> Result<f32, Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        self.average(adc, 1).map(|raw| self.to_units(raw))
+    }
+}
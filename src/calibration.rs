@@ -0,0 +1,475 @@
+//! Offset calibration table and calibration data
+//!
+//! The offset of the device depends on the gain, the PGA bypass and the data rate. The table
+//! holds the offsets of up to [`CalibrationTable::CAPACITY`] of these combinations, so that
+//! switching between settings does not require calibrating the offset again.
+//!
+//! [`CalibrationData`] holds the whole calibration state of the driver and encodes it into a
+//! compact binary blob for storage in flash or EEPROM.
+
+use crate::config::Config;
+use crate::interface::crc16;
+use crate::registers::*;
+
+/// Number of data rate and operating mode combinations
+const DATA_RATES: usize = 14;
+
+/// Settings an offset calibration is valid for
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationKey {
+    /// gain of the PGA (GAIN)
+    pub gain: Gain,
+    /// bypass the PGA (PGA_BYPASS)
+    pub pga_bypass: bool,
+    /// data rate (DR) and operating mode (MODE)
+    pub data_rate: DataRate,
+}
+
+impl CalibrationKey {
+    /// The key of the settings of a configuration
+    pub fn from_config(config: &Config) -> Self {
+        CalibrationKey {
+            gain: config.gain,
+            pga_bypass: config.pga_bypass,
+            data_rate: config.data_rate,
+        }
+    }
+
+    fn index(&self) -> usize {
+        ((self.gain as usize) * 2 + self.pga_bypass as usize) * DATA_RATES + self.data_rate as usize
+    }
+
+    fn from_index(index: usize) -> Option<Self> {
+        Some(CalibrationKey {
+            gain: Gain::try_from((index / DATA_RATES / 2) as u8).ok()?,
+            pga_bypass: (index / DATA_RATES) % 2 == 1,
+            data_rate: DataRate::try_from((index % DATA_RATES) as u8).ok()?,
+        })
+    }
+}
+
+/// Calibrated offsets keyed by gain, PGA bypass and data rate
+#[derive(Debug, Copy, Clone)]
+pub struct CalibrationTable {
+    entries: [(CalibrationKey, i32); CalibrationTable::CAPACITY],
+    len: usize,
+}
+
+impl Default for CalibrationTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PartialEq for CalibrationTable {
+    /// Tables are equal if they hold the same offsets, regardless of their order
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .all(|(key, offset)| other.offset(key) == Some(offset))
+    }
+}
+
+impl Eq for CalibrationTable {}
+
+impl CalibrationTable {
+    /// Maximum number of settings with a calibrated offset
+    pub const CAPACITY: usize = 8;
+
+    /// An empty table
+    pub const fn new() -> Self {
+        const UNUSED: (CalibrationKey, i32) = (
+            CalibrationKey {
+                gain: Gain::Gain1,
+                pga_bypass: false,
+                data_rate: DataRate::Sps20Normal,
+            },
+            0,
+        );
+        CalibrationTable {
+            entries: [UNUSED; Self::CAPACITY],
+            len: 0,
+        }
+    }
+
+    fn position(&self, key: CalibrationKey) -> Option<usize> {
+        self.entries[..self.len]
+            .iter()
+            .position(|(entry, _)| *entry == key)
+    }
+
+    /// The offset calibrated for the given settings
+    pub fn offset(&self, key: CalibrationKey) -> Option<i32> {
+        self.position(key).map(|index| self.entries[index].1)
+    }
+
+    /// Store the offset of the given settings. Fails with [`CalibrationError::TableFull`] if
+    /// the settings have no offset yet and the table already holds
+    /// [`CAPACITY`](Self::CAPACITY) offsets.
+    pub fn set_offset(&mut self, key: CalibrationKey, offset: i32) -> Result<(), CalibrationError> {
+        match self.position(key) {
+            Some(index) => self.entries[index].1 = offset,
+            None if self.len < Self::CAPACITY => {
+                self.entries[self.len] = (key, offset);
+                self.len += 1;
+            }
+            None => return Err(CalibrationError::TableFull),
+        }
+        Ok(())
+    }
+
+    /// Remove the offset of the given settings
+    pub fn remove(&mut self, key: CalibrationKey) {
+        if let Some(index) = self.position(key) {
+            self.entries.copy_within(index + 1..self.len, index);
+            self.len -= 1;
+        }
+    }
+
+    /// Remove all offsets
+    pub fn clear(&mut self) {
+        *self = Self::new();
+    }
+
+    /// Number of settings with a calibrated offset
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Whether no offset has been calibrated
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// All calibrated settings and their offsets, in the order they were first calibrated
+    pub fn iter(&self) -> impl Iterator<Item = (CalibrationKey, i32)> + '_ {
+        self.entries[..self.len].iter().copied()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for CalibrationTable {
+    /// Serialized as a sequence of the calibrated settings and their offsets
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.iter())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for CalibrationTable {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct Visitor;
+
+        impl<'de> serde::de::Visitor<'de> for Visitor {
+            type Value = CalibrationTable;
+
+            fn expecting(&self, formatter: &mut core::fmt::Formatter) -> core::fmt::Result {
+                formatter.write_str("a sequence of calibration keys and offsets")
+            }
+
+            fn visit_seq<A: serde::de::SeqAccess<'de>>(
+                self,
+                mut seq: A,
+            ) -> Result<Self::Value, A::Error> {
+                let mut table = CalibrationTable::new();
+                while let Some((key, offset)) = seq.next_element()? {
+                    table
+                        .set_offset(key, offset)
+                        .map_err(|_| serde::de::Error::invalid_length(table.len() + 1, &self))?;
+                }
+                Ok(table)
+            }
+        }
+
+        deserializer.deserialize_seq(Visitor)
+    }
+}
+
+/// Error storing an offset in the calibration table, or decoding or encoding a calibration blob
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum CalibrationError {
+    /// The buffer is too small for the blob
+    BufferTooSmall,
+    /// The blob has been written in an unsupported format version
+    UnsupportedVersion(u8),
+    /// The CRC of the blob does not match its content
+    CrcMismatch,
+    /// The blob holds an invalid entry
+    Malformed,
+    /// The calibration table has no room for another setting
+    TableFull,
+    /// A gain correction factor is not a finite, positive number
+    InvalidFactor,
+}
+
+/// Whether a gain correction factor is a finite, positive number
+pub(crate) fn is_valid_factor(factor: f32) -> bool {
+    factor.is_finite() && factor > 0.0
+}
+
+/// Size of the version, entry count, offset and gain corrections
+const HEADER_SIZE: usize = 1 + 1 + 4 + 8 * 4;
+/// Size of the index and offset of an entry of the calibration table
+const ENTRY_SIZE: usize = 1 + 4;
+
+/// Calibration state of the driver: the fallback offset, the calibration table and the gain
+/// correction factors.
+///
+/// The binary encoding holds, in little-endian byte order, the format version, the number of
+/// table entries, the offset, the eight gain correction factors, each table entry as index
+/// and offset, and a CRC-16-CCITT over all preceding bytes.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CalibrationData {
+    /// offset used for settings without an entry in the table
+    pub offset: i32,
+    /// offsets calibrated per gain, PGA bypass and data rate
+    pub table: CalibrationTable,
+    /// correction factors indexed by the [`Gain`]
+    pub gain_correction: [f32; 8],
+}
+
+impl Default for CalibrationData {
+    /// Uncalibrated state
+    fn default() -> Self {
+        CalibrationData {
+            offset: 0,
+            table: CalibrationTable::new(),
+            gain_correction: [1.0; 8],
+        }
+    }
+}
+
+impl CalibrationData {
+    /// Version of the binary encoding
+    pub const VERSION: u8 = 1;
+    /// Size of the largest binary encoding, with a full calibration table
+    pub const MAX_SIZE: usize = HEADER_SIZE + ENTRY_SIZE * CalibrationTable::CAPACITY + 2;
+
+    /// Size of the binary encoding
+    pub fn encoded_len(&self) -> usize {
+        HEADER_SIZE + ENTRY_SIZE * self.table.len() + 2
+    }
+
+    /// Check that all gain correction factors are finite and positive
+    pub fn validate(&self) -> Result<(), CalibrationError> {
+        if self.gain_correction.into_iter().all(is_valid_factor) {
+            Ok(())
+        } else {
+            Err(CalibrationError::InvalidFactor)
+        }
+    }
+
+    /// Encode into the beginning of `buffer`, returning the number of bytes written. Fails
+    /// with [`CalibrationError::InvalidFactor`] instead of writing a blob that
+    /// [`decode`](Self::decode) would reject.
+    pub fn encode(&self, buffer: &mut [u8]) -> Result<usize, CalibrationError> {
+        self.validate()?;
+        let len = self.encoded_len();
+        let buffer = buffer
+            .get_mut(..len)
+            .ok_or(CalibrationError::BufferTooSmall)?;
+        buffer[0] = Self::VERSION;
+        buffer[1] = self.table.len() as u8;
+        buffer[2..6].copy_from_slice(&self.offset.to_le_bytes());
+        for (chunk, factor) in buffer[6..HEADER_SIZE]
+            .chunks_exact_mut(4)
+            .zip(self.gain_correction)
+        {
+            chunk.copy_from_slice(&factor.to_le_bytes());
+        }
+        for (chunk, (key, offset)) in buffer[HEADER_SIZE..len - 2]
+            .chunks_exact_mut(ENTRY_SIZE)
+            .zip(self.table.iter())
+        {
+            chunk[0] = key.index() as u8;
+            chunk[1..].copy_from_slice(&offset.to_le_bytes());
+        }
+        let crc = crc16(&buffer[..len - 2]);
+        buffer[len - 2..].copy_from_slice(&crc.to_le_bytes());
+        Ok(len)
+    }
+
+    /// Decode from the beginning of `buffer`, trailing bytes are ignored
+    pub fn decode(buffer: &[u8]) -> Result<Self, CalibrationError> {
+        if buffer.len() < HEADER_SIZE + 2 {
+            return Err(CalibrationError::BufferTooSmall);
+        }
+        if buffer[0] != Self::VERSION {
+            return Err(CalibrationError::UnsupportedVersion(buffer[0]));
+        }
+        let len = HEADER_SIZE + ENTRY_SIZE * buffer[1] as usize + 2;
+        let buffer = buffer.get(..len).ok_or(CalibrationError::BufferTooSmall)?;
+        let crc = u16::from_le_bytes([buffer[len - 2], buffer[len - 1]]);
+        if crc16(&buffer[..len - 2]) != crc {
+            return Err(CalibrationError::CrcMismatch);
+        }
+        let mut data = CalibrationData {
+            offset: i32::from_le_bytes([buffer[2], buffer[3], buffer[4], buffer[5]]),
+            ..Default::default()
+        };
+        for (factor, chunk) in data
+            .gain_correction
+            .iter_mut()
+            .zip(buffer[6..HEADER_SIZE].chunks_exact(4))
+        {
+            *factor = f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+            if !is_valid_factor(*factor) {
+                return Err(CalibrationError::Malformed);
+            }
+        }
+        for chunk in buffer[HEADER_SIZE..len - 2].chunks_exact(ENTRY_SIZE) {
+            let key =
+                CalibrationKey::from_index(chunk[0] as usize).ok_or(CalibrationError::Malformed)?;
+            let offset = i32::from_le_bytes([chunk[1], chunk[2], chunk[3], chunk[4]]);
+            data.table.set_offset(key, offset)?;
+        }
+        Ok(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(gain: Gain) -> CalibrationKey {
+        CalibrationKey {
+            gain,
+            pga_bypass: false,
+            data_rate: DataRate::Sps20Normal,
+        }
+    }
+
+    #[test]
+    fn key_index_round_trip() {
+        for index in 0..8 * 2 * DATA_RATES {
+            let key = CalibrationKey::from_index(index).unwrap();
+            assert_eq!(key.index(), index);
+        }
+        assert_eq!(CalibrationKey::from_index(8 * 2 * DATA_RATES), None);
+    }
+
+    #[test]
+    fn table_capacity() {
+        let mut table = CalibrationTable::new();
+        let gains = (0..8).map(|bits| Gain::try_from(bits).unwrap());
+        for (offset, gain) in gains.clone().enumerate() {
+            table.set_offset(key(gain), offset as i32).unwrap();
+        }
+        assert_eq!(table.len(), CalibrationTable::CAPACITY);
+        let other = CalibrationKey {
+            pga_bypass: true,
+            ..key(Gain::Gain1)
+        };
+        assert_eq!(table.set_offset(other, 1), Err(CalibrationError::TableFull));
+        // updating a calibrated setting does not need room
+        table.set_offset(key(Gain::Gain4), -5).unwrap();
+        assert_eq!(table.offset(key(Gain::Gain4)), Some(-5));
+        table.remove(key(Gain::Gain2));
+        assert_eq!(table.offset(key(Gain::Gain2)), None);
+        assert_eq!(table.offset(key(Gain::Gain8)), Some(3));
+        table.set_offset(other, 1).unwrap();
+        assert_eq!(table.iter().last(), Some((other, 1)));
+        table.clear();
+        assert!(table.is_empty());
+    }
+
+    fn data() -> CalibrationData {
+        let mut data = CalibrationData {
+            offset: -42,
+            ..Default::default()
+        };
+        data.gain_correction[Gain::Gain16 as usize] = 1.0125;
+        data.table.set_offset(key(Gain::Gain1), 100).unwrap();
+        let other = CalibrationKey {
+            gain: Gain::Gain128,
+            pga_bypass: true,
+            data_rate: DataRate::Sps2000Turbo,
+        };
+        data.table.set_offset(other, -0x7F_FFFF).unwrap();
+        data
+    }
+
+    #[test]
+    fn encode_decode_round_trip() {
+        let mut buffer = [0; CalibrationData::MAX_SIZE];
+        let len = data().encode(&mut buffer).unwrap();
+        assert_eq!(len, data().encoded_len());
+        assert_eq!(CalibrationData::decode(&buffer[..len]), Ok(data()));
+        // trailing bytes are ignored
+        assert_eq!(CalibrationData::decode(&buffer), Ok(data()));
+        assert_eq!(
+            CalibrationData::decode(&buffer[..len - 1]),
+            Err(CalibrationError::BufferTooSmall)
+        );
+        assert_eq!(
+            data().encode(&mut buffer[..len - 1]),
+            Err(CalibrationError::BufferTooSmall)
+        );
+    }
+
+    #[test]
+    fn decode_bad_crc() {
+        let mut buffer = [0; CalibrationData::MAX_SIZE];
+        let len = data().encode(&mut buffer).unwrap();
+        for index in 2..len {
+            let mut corrupt = buffer;
+            corrupt[index] ^= 0x10;
+            assert_eq!(
+                CalibrationData::decode(&corrupt),
+                Err(CalibrationError::CrcMismatch)
+            );
+        }
+    }
+
+    #[test]
+    fn decode_bad_version() {
+        let mut buffer = [0; CalibrationData::MAX_SIZE];
+        data().encode(&mut buffer).unwrap();
+        buffer[0] = CalibrationData::VERSION + 1;
+        assert_eq!(
+            CalibrationData::decode(&buffer),
+            Err(CalibrationError::UnsupportedVersion(
+                CalibrationData::VERSION + 1
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_factors() {
+        let mut buffer = [0; CalibrationData::MAX_SIZE];
+        for factor in [-1.0, 0.0, f32::NAN, f32::INFINITY] {
+            let mut data = data();
+            data.gain_correction[Gain::Gain2 as usize] = factor;
+            assert_eq!(data.validate(), Err(CalibrationError::InvalidFactor));
+            assert_eq!(
+                data.encode(&mut buffer),
+                Err(CalibrationError::InvalidFactor)
+            );
+        }
+        // a blob with an invalid factor and a matching CRC is rejected as well
+        let len = data().encode(&mut buffer).unwrap();
+        buffer[6..10].copy_from_slice(&(-1.0f32).to_le_bytes());
+        let crc = crc16(&buffer[..len - 2]);
+        buffer[len - 2..len].copy_from_slice(&crc.to_le_bytes());
+        assert_eq!(
+            CalibrationData::decode(&buffer),
+            Err(CalibrationError::Malformed)
+        );
+    }
+
+    #[test]
+    fn table_eq_ignores_order() {
+        let mut a = CalibrationTable::new();
+        let mut b = CalibrationTable::new();
+        a.set_offset(key(Gain::Gain1), 1).unwrap();
+        a.set_offset(key(Gain::Gain2), 2).unwrap();
+        b.set_offset(key(Gain::Gain2), 2).unwrap();
+        b.set_offset(key(Gain::Gain1), 1).unwrap();
+        assert_eq!(a, b);
+        b.set_offset(key(Gain::Gain1), 3).unwrap();
+        assert_ne!(a, b);
+    }
+}
@@ -0,0 +1,213 @@
+//! Whole-device configuration
+
+use crate::registers::*;
+
+/// Configuration of all settings held in the config registers of the device
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Config {
+    /// voltage reference (VREF)
+    pub v_ref: VRef,
+    /// gain of the PGA (GAIN)
+    pub gain: Gain,
+    /// input multiplexer (MUX)
+    pub mux: Mux,
+    /// excitation current level (IDAC)
+    pub current_source: CurrentSource,
+    /// routing of excitation current source 1 (I1MUX)
+    pub current_route_1: CurrentRoute,
+    /// routing of excitation current source 2 (I2MUX)
+    pub current_route_2: CurrentRoute,
+    /// data rate (DR) and operating mode (MODE)
+    pub data_rate: DataRate,
+    /// bypass the PGA (PGA_BYPASS)
+    pub pga_bypass: bool,
+    /// conversion mode (CM)
+    pub conversion_mode: ConversionMode,
+    /// temperature sensor mode (TS)
+    pub temperature_sensor_mode: bool,
+    /// data counter enable (DCNT)
+    pub data_counter_enable: bool,
+    /// data integrity check (CRC)
+    pub crc: Crc,
+    /// 10 uA burn-out current sources (BCS)
+    pub burn_out_current_sources: bool,
+}
+
+impl Default for Config {
+    /// Power-on defaults of the device
+    fn default() -> Self {
+        Config {
+            v_ref: VRef::Internal,
+            gain: Gain::Gain1,
+            mux: Mux::Ain0Ain1,
+            current_source: CurrentSource::Off,
+            current_route_1: CurrentRoute::Off,
+            current_route_2: CurrentRoute::Off,
+            data_rate: DataRate::Sps20Normal,
+            pga_bypass: false,
+            conversion_mode: ConversionMode::SingleShot,
+            temperature_sensor_mode: false,
+            data_counter_enable: false,
+            crc: Crc::Disabled,
+            burn_out_current_sources: false,
+        }
+    }
+}
+
+impl Config {
+    /// Addresses of the config registers described by the configuration
+    pub const ADDRESSES: [u8; 4] = [
+        Config0::ADDRESS,
+        Config1::ADDRESS,
+        Config2::ADDRESS,
+        Config3::ADDRESS,
+    ];
+
+    /// Config register 0
+    pub fn config0(&self) -> Config0 {
+        Config0 {
+            mux: self.mux,
+            gain: self.gain,
+            pga_bypass: self.pga_bypass,
+        }
+    }
+
+    /// Config register 1
+    pub fn config1(&self) -> Config1 {
+        Config1 {
+            data_rate: self.data_rate,
+            conversion_mode: self.conversion_mode,
+            v_ref: self.v_ref.source(),
+            temperature_sensor_mode: self.temperature_sensor_mode,
+        }
+    }
+
+    /// Config register 2
+    pub fn config2(&self) -> Config2 {
+        Config2 {
+            data_ready: false,
+            data_counter: self.data_counter_enable,
+            crc: self.crc,
+            burn_out_current_sources: self.burn_out_current_sources,
+            current_source: self.current_source,
+        }
+    }
+
+    /// Config register 3
+    pub fn config3(&self) -> Config3 {
+        Config3 {
+            current_route_1: self.current_route_1,
+            current_route_2: self.current_route_2,
+            auto_read: false,
+        }
+    }
+
+    /// Encode the config register at the given address
+    pub fn register(&self, address: u8) -> Option<u8> {
+        match address {
+            Config0::ADDRESS => Some(self.config0().to_bits()),
+            Config1::ADDRESS => Some(self.config1().to_bits()),
+            Config2::ADDRESS => Some(self.config2().to_bits()),
+            Config3::ADDRESS => Some(self.config3().to_bits()),
+            _ => None,
+        }
+    }
+
+    /// Decode the values of config registers 0 to 3. The voltage of an external or analog
+    /// supply reference is not stored on the device and is taken from `v_ref_voltage`.
+    pub fn from_registers(registers: [u8; 4], v_ref_voltage: f32) -> Result<Self, DecodeError> {
+        let config0 = Config0::from_bits(registers[0])?;
+        let config1 = Config1::from_bits(registers[1])?;
+        let config2 = Config2::from_bits(registers[2])?;
+        let config3 = Config3::from_bits(registers[3])?;
+        Ok(Config {
+            v_ref: config1.v_ref.with_voltage(v_ref_voltage),
+            gain: config0.gain,
+            mux: config0.mux,
+            current_source: config2.current_source,
+            current_route_1: config3.current_route_1,
+            current_route_2: config3.current_route_2,
+            data_rate: config1.data_rate,
+            pga_bypass: config0.pga_bypass,
+            conversion_mode: config1.conversion_mode,
+            temperature_sensor_mode: config1.temperature_sensor_mode,
+            data_counter_enable: config2.data_counter,
+            crc: config2.crc,
+            burn_out_current_sources: config2.burn_out_current_sources,
+        })
+    }
+}
+
+/// Shadow copy of the config registers with a dirty flag per register. A register is dirty
+/// while its shadow value has not been written to the device successfully.
+#[derive(Debug, Copy, Clone, PartialEq, Default)]
+pub struct ShadowRegisters {
+    pub(crate) config: Config,
+    dirty: u8,
+}
+
+impl ShadowRegisters {
+    /// Shadow registers holding `config` that is known to be present on the device
+    pub fn new(config: Config) -> Self {
+        ShadowRegisters { config, dirty: 0 }
+    }
+
+    /// The configuration described by the shadow registers
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Whether the register at the given address still has to be written to the device,
+    /// `false` for addresses without a config register
+    pub fn is_dirty(&self, address: u8) -> bool {
+        self.dirty.checked_shr(address as u32).unwrap_or(0) & 0b1 == 1
+    }
+
+    /// Addresses of the registers that still have to be written to the device
+    pub fn dirty_registers(&self) -> impl Iterator<Item = u8> + '_ {
+        Config::ADDRESSES
+            .into_iter()
+            .chain([Config4::ADDRESS])
+            .filter(|address| self.is_dirty(*address))
+    }
+
+    /// Mark a register as differing from the device
+    pub(crate) fn mark_dirty(&mut self, address: u8) {
+        self.dirty |= 1u8.checked_shl(address as u32).unwrap_or(0);
+    }
+
+    /// Mark a register as written to the device
+    pub(crate) fn mark_clean(&mut self, address: u8) {
+        self.dirty &= !1u8.checked_shl(address as u32).unwrap_or(0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dirty_flags() {
+        let mut shadow = ShadowRegisters::default();
+        shadow.mark_dirty(Config1::ADDRESS);
+        shadow.mark_dirty(Config4::ADDRESS);
+        assert!(shadow.is_dirty(Config1::ADDRESS));
+        assert!(!shadow.is_dirty(Config0::ADDRESS));
+        assert!(shadow
+            .dirty_registers()
+            .eq([Config1::ADDRESS, Config4::ADDRESS]));
+        shadow.mark_clean(Config1::ADDRESS);
+        assert!(shadow.dirty_registers().eq([Config4::ADDRESS]));
+    }
+
+    #[test]
+    fn unknown_addresses() {
+        let mut shadow = ShadowRegisters::default();
+        for address in [8, 100, u8::MAX] {
+            shadow.mark_dirty(address);
+            assert!(!shadow.is_dirty(address));
+            shadow.mark_clean(address);
+        }
+        assert_eq!(shadow.dirty_registers().count(), 0);
+    }
+}
@@ -0,0 +1,143 @@
+//! GPIO pins of the ADS122U04
+//!
+//! The UART variant has three general purpose pins, configured in config register 4. GPIO2
+//! can alternatively act as the data ready output. The pins can be split out of a driver
+//! shared in a [`RefCell`] as handles implementing the embedded-hal digital pin traits.
+
+use core::cell::RefCell;
+
+use embedded_hal::digital::{ErrorType, InputPin, OutputPin};
+use embedded_io::{Read, Write};
+
+use crate::registers::*;
+use crate::{Error, ADS122U04};
+
+/// General purpose pin of the ADS122U04
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Gpio {
+    /// GPIO0
+    Gpio0 = 0,
+    /// GPIO1
+    Gpio1 = 1,
+    /// GPIO2, also usable as DRDY output
+    Gpio2 = 2,
+}
+
+impl<UART, DRDY, E> ADS122U04<UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    DRDY: InputPin,
+{
+    /// Set the GPIO configuration (config register 4)
+    pub fn set_gpio_config(&mut self, config: Config4) -> Result<(), Error<E>> {
+        self.gpio = config;
+        self.update_reg(Config4::ADDRESS)
+    }
+
+    /// Read the GPIO configuration (config register 4). The data of input pins reflects
+    /// their levels.
+    pub fn get_gpio_config(&mut self) -> Result<Config4, Error<E>> {
+        Ok(Config4::from_bits(self.read_reg(Config4::ADDRESS)?))
+    }
+
+    /// Set the direction of a pin (GPIOxDIR), `true` for output
+    pub fn set_gpio_direction(&mut self, pin: Gpio, output: bool) -> Result<(), Error<E>> {
+        self.gpio.gpio_output[pin as usize] = output;
+        self.update_reg(Config4::ADDRESS)
+    }
+
+    /// Let GPIO2 act as DRDY output (GPIO2SEL). GPIO2 must be configured as output.
+    pub fn set_gpio2_drdy(&mut self, enable: bool) -> Result<(), Error<E>> {
+        self.gpio.gpio2_drdy = enable;
+        self.update_reg(Config4::ADDRESS)
+    }
+
+    /// Drive an output pin high or low (GPIOxDAT)
+    pub fn set_gpio_level(&mut self, pin: Gpio, high: bool) -> Result<(), Error<E>> {
+        self.gpio.gpio_data[pin as usize] = high;
+        self.update_reg(Config4::ADDRESS)
+    }
+
+    /// Read the level of a pin (GPIOxDAT)
+    pub fn get_gpio_level(&mut self, pin: Gpio) -> Result<bool, Error<E>> {
+        Ok(self.get_gpio_config()?.gpio_data[pin as usize])
+    }
+}
+
+/// Handle of a single GPIO pin of a driver shared in a [`RefCell`]. The direction of the pin
+/// is not changed by the handle and has to be configured on the driver.
+pub struct GpioPin<'a, UART, DRDY> {
+    adc: &'a RefCell<ADS122U04<UART, DRDY>>,
+    pin: Gpio,
+}
+
+impl<'a, UART, DRDY> GpioPin<'a, UART, DRDY> {
+    /// Handle of the given pin of the shared driver
+    pub fn new(adc: &'a RefCell<ADS122U04<UART, DRDY>>, pin: Gpio) -> Self {
+        GpioPin { adc, pin }
+    }
+
+    /// The pin of the handle
+    pub fn pin(&self) -> Gpio {
+        self.pin
+    }
+}
+
+/// Handles of all GPIO pins of a driver shared in a [`RefCell`]
+pub struct GpioPins<'a, UART, DRDY> {
+    /// GPIO0
+    pub gpio0: GpioPin<'a, UART, DRDY>,
+    /// GPIO1
+    pub gpio1: GpioPin<'a, UART, DRDY>,
+    /// GPIO2
+    pub gpio2: GpioPin<'a, UART, DRDY>,
+}
+
+impl<'a, UART, DRDY> GpioPins<'a, UART, DRDY> {
+    /// Split the pins out of the shared driver
+    pub fn new(adc: &'a RefCell<ADS122U04<UART, DRDY>>) -> Self {
+        GpioPins {
+            gpio0: GpioPin::new(adc, Gpio::Gpio0),
+            gpio1: GpioPin::new(adc, Gpio::Gpio1),
+            gpio2: GpioPin::new(adc, Gpio::Gpio2),
+        }
+    }
+}
+
+impl<UART, DRDY, E> ErrorType for GpioPin<'_, UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    E: core::fmt::Debug,
+{
+    type Error = Error<E>;
+}
+
+impl<UART, DRDY, E> OutputPin for GpioPin<'_, UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    DRDY: InputPin,
+    E: core::fmt::Debug,
+{
+    fn set_low(&mut self) -> Result<(), Self::Error> {
+        self.adc.borrow_mut().set_gpio_level(self.pin, false)
+    }
+
+    fn set_high(&mut self) -> Result<(), Self::Error> {
+        self.adc.borrow_mut().set_gpio_level(self.pin, true)
+    }
+}
+
+impl<UART, DRDY, E> InputPin for GpioPin<'_, UART, DRDY>
+where
+    UART: Write<Error = E> + Read<Error = E>,
+    DRDY: InputPin,
+    E: core::fmt::Debug,
+{
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        self.adc.borrow_mut().get_gpio_level(self.pin)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        self.is_high().map(|high| !high)
+    }
+}
@@ -1,7 +1,9 @@
 //! I2C/UART interfaces
 
-use embedded_hal::i2c;
-use embedded_io::{Read, Write};
+use core::convert::Infallible;
+
+use embedded_hal::{digital, i2c};
+use embedded_io::{Read, ReadExactError, Write};
 
 use crate::registers::*;
 use crate::{private, Error};
@@ -19,8 +21,62 @@ pub struct SerialInterface<UART> {
     pub(crate) serial: UART,
 }
 
+/// Placeholder for a device without a connected DRDY pin
+#[derive(Debug)]
+pub enum NoDrdy {}
+
+impl digital::ErrorType for NoDrdy {
+    type Error = Infallible;
+}
+
+impl digital::InputPin for NoDrdy {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        match *self {}
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        match *self {}
+    }
+}
+
+/// ADS122C04, the I2C variant
+#[derive(Debug)]
+pub enum Ads122C04 {}
+
+/// ADS122U04, the UART variant with GPIOs (config register 4) and automatic data read mode
+#[derive(Debug)]
+pub enum Ads122U04 {}
+
+/// Device variant
+pub trait Variant: private::Sealed {
+    /// Address of the last config register
+    const LAST_REGISTER: u8;
+}
+
+impl Variant for Ads122C04 {
+    const LAST_REGISTER: u8 = Config3::ADDRESS;
+}
+
+impl Variant for Ads122U04 {
+    const LAST_REGISTER: u8 = Config4::ADDRESS;
+}
+
+/// Bus interface of a device variant
+pub trait Interface: private::Sealed {
+    /// The variant connected through the interface
+    type Variant: Variant;
+}
+
+impl<I2C> Interface for I2cInterface<I2C> {
+    type Variant = Ads122C04;
+}
+
+impl<UART> Interface for SerialInterface<UART> {
+    type Variant = Ads122U04;
+}
+
 /// Write data
-pub trait WriteData: private::Sealed {
+pub trait WriteData: Interface {
     /// Error type
     type Error;
     /// Write to an u8 register
@@ -54,7 +110,7 @@ where
 {
     type Error = Error<E>;
     fn write_register(&mut self, register: u8, data: u8) -> Result<(), Self::Error> {
-        let register = Commands::WReg as u8 | (register << 2); // write command
+        let register = Commands::WReg as u8 | (register << 1); // write command (rrrx)
         self.serial
             .write_all(&[0x55, register, data])
             .map_err(Error::CommError)?;
@@ -70,13 +126,68 @@ where
 }
 
 /// Read data
-pub trait ReadData: private::Sealed {
+pub trait ReadData: Interface {
     /// Error type
     type Error;
-    /// Read an u8 register
-    fn read_register(&mut self, register: u8) -> Result<u8, Self::Error>;
-    /// Read some data. The first element corresponds to the starting address.
-    fn read_data(&mut self) -> Result<u32, Self::Error>;
+    /// Read an u8 register, followed by its integrity bytes if `crc` is enabled
+    fn read_register(&mut self, register: u8, crc: Crc) -> Result<u8, Self::Error>;
+    /// Read the conversion data, preceded by the conversion counter if `data_counter` is
+    /// enabled and followed by its integrity bytes if `crc` is enabled
+    fn read_data(&mut self, crc: Crc, data_counter: bool)
+        -> Result<(u32, Option<u8>), Self::Error>;
+}
+
+/// Split a conversion data frame into the 24-bit value and the optional conversion counter
+pub(crate) fn decode_data(buffer: &[u8], data_counter: bool) -> (u32, Option<u8>) {
+    let (counter, data) = if data_counter {
+        (Some(buffer[0]), &buffer[1..])
+    } else {
+        (None, buffer)
+    };
+    let msb = data[0];
+    let csb = data[1];
+    let lsb = data[2];
+    (
+        (msb as u32) << 16 | (csb as u32) << 8 | (lsb as u32),
+        counter,
+    )
+}
+
+/// Number of integrity bytes that follow `len` data bytes in the given CRC mode
+pub(crate) fn integrity_len(crc: Crc, len: usize) -> usize {
+    match crc {
+        Crc::Disabled => 0,
+        Crc::Inverted => len,
+        Crc::Crc16 => 2,
+    }
+}
+
+/// CRC-16-CCITT (polynomial 0x1021, initial value 0xFFFF) as computed by the device
+pub(crate) fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ ((byte as u16) << 8), |crc, _| {
+            if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            }
+        })
+    })
+}
+
+/// Verify the integrity bytes that follow the first `len` data bytes of `buffer`
+pub(crate) fn check_integrity<E>(buffer: &[u8], len: usize, crc: Crc) -> Result<(), Error<E>> {
+    let (data, check) = buffer.split_at(len);
+    let valid = match crc {
+        Crc::Disabled => true,
+        Crc::Inverted => data.iter().zip(check).all(|(d, c)| *d == !*c),
+        Crc::Crc16 => crc16(data) == ((check[0] as u16) << 8 | (check[1] as u16)),
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::CrcMismatch)
+    }
 }
 
 impl<I2C, E> ReadData for I2cInterface<I2C>
@@ -84,26 +195,30 @@ where
     I2C: i2c::I2c<Error = E>,
 {
     type Error = Error<E>;
-    fn read_register(&mut self, register: u8) -> Result<u8, Self::Error> {
+    fn read_register(&mut self, register: u8, crc: Crc) -> Result<u8, Self::Error> {
         let register = Commands::RReg as u8 | (register << 2); // read command
-        let mut buffer = [0];
+        let mut buffer = [0; 3];
+        let len = 1 + integrity_len(crc, 1);
         self.i2c
-            .write_read(self.address, &[register], &mut buffer)
-            .map(|_| buffer[0])
-            .map_err(Error::CommError)
+            .write_read(self.address, &[register], &mut buffer[..len])
+            .map_err(Error::CommError)?;
+        check_integrity(&buffer[..len], 1, crc)?;
+        Ok(buffer[0])
     }
 
-    fn read_data(&mut self) -> Result<u32, Self::Error> {
-        let mut buffer = [0, 0, 0];
+    fn read_data(
+        &mut self,
+        crc: Crc,
+        data_counter: bool,
+    ) -> Result<(u32, Option<u8>), Self::Error> {
+        let mut buffer = [0; 8];
+        let data_len = 3 + data_counter as usize;
+        let len = data_len + integrity_len(crc, data_len);
         self.i2c
-            .write_read(self.address, &[Commands::RData as u8], &mut buffer)
-            .map(|_| {
-                let msb = buffer[0];
-                let csb = buffer[1];
-                let lsb = buffer[2];
-                (msb as u32) << 16 | (csb as u32) << 8 | (lsb as u32)
-            })
-            .map_err(Error::CommError)
+            .write_read(self.address, &[Commands::RData as u8], &mut buffer[..len])
+            .map_err(Error::CommError)?;
+        check_integrity(&buffer[..len], data_len, crc)?;
+        Ok(decode_data(&buffer, data_counter))
     }
 }
 
@@ -112,27 +227,131 @@ where
     UART: Write<Error = E> + Read<Error = E>,
 {
     type Error = Error<E>;
-    fn read_register(&mut self, register: u8) -> Result<u8, Self::Error> {
-        let register = Commands::RReg as u8 | (register << 2); // read command
+    fn read_register(&mut self, register: u8, crc: Crc) -> Result<u8, Self::Error> {
+        let register = Commands::RReg as u8 | (register << 1); // read command (rrrx)
         self.serial
             .write_all(&[0x55, register])
             .map_err(Error::CommError)?;
         self.serial.flush().map_err(Error::CommError)?;
 
-        let mut out = [0];
-        self.serial.read(&mut out).map_err(Error::CommError)?;
+        let mut out = [0; 3];
+        let len = 1 + integrity_len(crc, 1);
+        self.read_exact(&mut out[..len])?;
+        check_integrity(&out[..len], 1, crc)?;
 
         Ok(out[0])
     }
 
-    fn read_data(&mut self) -> Result<u32, Self::Error> {
-        let mut out = [0, 0, 0];
+    fn read_data(
+        &mut self,
+        crc: Crc,
+        data_counter: bool,
+    ) -> Result<(u32, Option<u8>), Self::Error> {
+        let mut out = [0; 8];
+        let data_len = 3 + data_counter as usize;
+        let len = data_len + integrity_len(crc, data_len);
         self.serial
             .write_all(&[0x55, Commands::RData as u8])
             .map_err(Error::CommError)?;
         self.serial.flush().map_err(Error::CommError)?;
-        self.serial.read(&mut out).map_err(Error::CommError)?;
-        let [msb, csb, lsb] = out;
-        Ok((msb as u32) << 16 | (csb as u32) << 8 | (lsb as u32))
+        self.read_exact(&mut out[..len])?;
+        check_integrity(&out[..len], data_len, crc)?;
+        Ok(decode_data(&out, data_counter))
+    }
+}
+
+impl<UART, E> SerialInterface<UART>
+where
+    UART: Read<Error = E>,
+{
+    /// Read a conversion data frame sent by the device in automatic data read mode.
+    /// If the integrity check fails, e.g. because a byte was lost on the line, the frame is
+    /// realigned by discarding one byte at a time, for at most one frame length.
+    pub(crate) fn read_auto_data(
+        &mut self,
+        crc: Crc,
+        data_counter: bool,
+    ) -> Result<(u32, Option<u8>), Error<E>> {
+        let mut out = [0; 8];
+        let data_len = 3 + data_counter as usize;
+        let len = data_len + integrity_len(crc, data_len);
+        self.read_exact(&mut out[..len])?;
+        for _ in 0..len {
+            if check_integrity::<E>(&out[..len], data_len, crc).is_ok() {
+                break;
+            }
+            out.copy_within(1..len, 0);
+            self.read_exact(&mut out[len - 1..len])?;
+        }
+        check_integrity(&out[..len], data_len, crc)?;
+        Ok(decode_data(&out, data_counter))
+    }
+
+    /// Fill the buffer, the response may arrive in several chunks
+    fn read_exact(&mut self, buffer: &mut [u8]) -> Result<(), Error<E>> {
+        self.serial.read_exact(buffer).map_err(|e| match e {
+            ReadExactError::UnexpectedEof => Error::Timeout,
+            ReadExactError::Other(e) => Error::CommError(e),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc16_known_answer() {
+        // CRC-16/CCITT-FALSE check value
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+        assert_eq!(crc16(&[]), 0xFFFF);
+    }
+
+    #[test]
+    fn check_crc16() {
+        let data = [0x12, 0x34, 0x56];
+        let crc = crc16(&data);
+        let mut frame = [data[0], data[1], data[2], (crc >> 8) as u8, crc as u8];
+        assert_eq!(check_integrity::<()>(&frame, 3, Crc::Crc16), Ok(()));
+        frame[1] ^= 0x01;
+        assert_eq!(
+            check_integrity::<()>(&frame, 3, Crc::Crc16),
+            Err(Error::CrcMismatch)
+        );
+    }
+
+    #[test]
+    fn check_inverted() {
+        let mut frame = [0x12, 0x34, 0x56, !0x12, !0x34, !0x56];
+        assert_eq!(check_integrity::<()>(&frame, 3, Crc::Inverted), Ok(()));
+        frame[4] = 0x34;
+        assert_eq!(
+            check_integrity::<()>(&frame, 3, Crc::Inverted),
+            Err(Error::CrcMismatch)
+        );
+    }
+
+    #[test]
+    fn check_disabled() {
+        assert_eq!(
+            check_integrity::<()>(&[0x12, 0x34, 0x56], 3, Crc::Disabled),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn integrity_lengths() {
+        assert_eq!(integrity_len(Crc::Disabled, 4), 0);
+        assert_eq!(integrity_len(Crc::Inverted, 4), 4);
+        assert_eq!(integrity_len(Crc::Crc16, 4), 2);
+    }
+
+    #[test]
+    fn decode_frames() {
+        assert_eq!(decode_data(&[0x12, 0x34, 0x56], false), (0x123456, None));
+        assert_eq!(
+            decode_data(&[0x07, 0x12, 0x34, 0x56], true),
+            (0x123456, Some(0x07))
+        );
     }
 }
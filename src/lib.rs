@@ -8,14 +8,33 @@ use core::fmt::Debug;
 use core::result::Result;
 use core::result::Result::Err;
 
-use embedded_hal::i2c;
+use embedded_hal::{digital, digital::InputPin, i2c};
 use embedded_io::{Read, Write};
 
-use crate::interface::{I2cInterface, ReadData, SerialInterface, WriteData};
+use crate::address::Address;
+use crate::calibration::{
+    is_valid_factor, CalibrationData, CalibrationError, CalibrationKey, CalibrationTable,
+};
+use crate::config::{Config, ShadowRegisters};
+use crate::interface::{I2cInterface, NoDrdy, ReadData, SerialInterface, Variant, WriteData};
 use crate::registers::*;
 
+pub mod address;
+#[cfg(feature = "async")]
+pub mod asynch;
+pub mod auto_read;
+pub mod bridge;
+pub mod calibration;
+pub mod config;
+pub mod gpio;
 pub mod interface;
 pub mod registers;
+pub mod rtd;
+pub mod scan;
+#[cfg(feature = "sim")]
+pub mod sim;
+pub mod thermistor;
+pub mod thermocouple;
 
 mod private {
     use super::interface;
@@ -25,6 +44,10 @@ mod private {
     impl<UART> Sealed for interface::SerialInterface<UART> {}
 
     impl<I2C> Sealed for interface::I2cInterface<I2C> {}
+
+    impl Sealed for interface::Ads122C04 {}
+
+    impl Sealed for interface::Ads122U04 {}
 }
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -38,29 +61,355 @@ pub enum Error<E> {
     InvalidValue,
     /// A timeout has occurred
     Timeout,
+    /// Reading the DRDY pin failed
+    PinError,
+    /// The data integrity check (CRC or inverted data) failed
+    CrcMismatch,
+    /// A register holds a reserved or invalid bit pattern
+    Decode(DecodeError),
+    /// A calibration blob could not be encoded or decoded
+    Calibration(CalibrationError),
+    /// A config register read back after writing does not hold the written value
+    VerifyMismatch {
+        /// register address
+        register: u8,
+        /// written value
+        expected: u8,
+        /// value read back
+        actual: u8,
+    },
     /// A communication error has occured
     CommError(E),
 }
 
+impl<E> From<DecodeError> for Error<E> {
+    fn from(e: DecodeError) -> Self {
+        Error::Decode(e)
+    }
+}
+
+impl<E> From<CalibrationError> for Error<E> {
+    fn from(e: CalibrationError) -> Self {
+        Error::Calibration(e)
+    }
+}
+
+impl<E: Debug> digital::Error for Error<E> {
+    fn kind(&self) -> digital::ErrorKind {
+        digital::ErrorKind::Other
+    }
+}
+
+/// Continuity of a [`Sample`] relative to the previously read one, derived from the
+/// conversion counter
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum Continuity {
+    /// The data counter is disabled or there is no previous sample to compare against
+    Unknown,
+    /// The sample is the conversion directly following the previous one
+    Consecutive,
+    /// The previous conversion has been read again
+    Repeated,
+    /// The given number of conversions have been missed since the previous sample
+    Skipped(u8),
+}
+
+impl Continuity {
+    fn from_counters(previous: Option<u8>, current: Option<u8>) -> Self {
+        match (previous, current) {
+            (Some(previous), Some(current)) => match current.wrapping_sub(previous) {
+                0 => Continuity::Repeated,
+                1 => Continuity::Consecutive,
+                n => Continuity::Skipped(n - 1),
+            },
+            _ => Continuity::Unknown,
+        }
+    }
+}
+
+/// A single conversion result
+#[derive(Debug, PartialEq, Copy, Clone)]
+pub struct Sample {
+    /// Signed ADC value with the offset subtracted
+    pub raw: i32,
+    /// Conversion counter, if the data counter (DCNT) is enabled
+    pub counter: Option<u8>,
+    /// Continuity relative to the previously read sample
+    pub continuity: Continuity,
+    /// Gain configured when the sample was read
+    pub gain: Gain,
+    /// Voltage reference configured when the sample was read
+    pub v_ref: VRef,
+    /// Correction factor of the gain, determined by
+    /// [`calibrate_gain`](ADS122x04::calibrate_gain)
+    pub gain_correction: f32,
+}
+
+impl Sample {
+    /// Convert the sample to the input voltage in V, using the gain and voltage reference
+    /// that were configured when it was read and the gain correction
+    pub fn to_voltage(&self) -> f32 {
+        raw_to_voltage(self.raw, self.v_ref, self.gain) * self.gain_correction
+    }
+}
+
+/// How a sensor circuit shows an open or shorted sensor at the limits of the input range
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub(crate) enum SensorLimits {
+    /// The result saturates at positive full scale when the sensor is open and is not
+    /// positive when it is shorted
+    OpenHigh,
+    /// The result is not positive when the sensor is open and saturates at positive full
+    /// scale when it is shorted, e.g. a thermistor between the measured node and AVDD
+    OpenLow,
+    /// The result saturates at either full scale when the sensor is open, e.g. a
+    /// thermocouple with the burn-out current sources enabled, and may take either sign
+    Bipolar,
+}
+
+/// Number of DRDY bit reads over the bus before waiting for data times out
+const DRDY_BIT_POLLS: u32 = 1000;
+
+/// Default number of DRDY pin reads before waiting for data times out. How long this takes
+/// depends on the CPU and GPIO speed, see [`ADS122x04::with_drdy_timeout`].
+const DRDY_PIN_POLLS: u32 = 10_000_000;
+
+/// Convert a signed ADC value to the input voltage in V
+pub(crate) fn raw_to_voltage(raw: i32, v_ref: VRef, gain: Gain) -> f32 {
+    let full_scale = v_ref.to_voltage() as f64 / gain.to_factor() as f64;
+    (full_scale / ((1 << 23) as f64) * (raw as f64)) as f32
+}
+
 /// Device handler for ADS122x04
-pub struct ADS122x04<BUS> {
+pub struct ADS122x04<BUS, DRDY = NoDrdy> {
     bus: BUS,
-    /// offset of the ADC
+    drdy: Option<DRDY>,
+    drdy_polls: u32,
+    /// offset of the ADC, used for settings without an entry in the calibration table
     pub offset: i32,
-    v_ref: VRef,
-    gain: Gain,
-    mux: Mux,
-    current_source: CurrentSource,
-    current_route_1: CurrentRoute,
-    current_route_2: CurrentRoute,
-    data_rate: DataRate,
-    pga_bypass: bool,
-    turbo_mode: bool,
-    conversion_mode: ConversionMode,
-    temperature_sensor_mode: bool,
-    data_counter_enable: bool,
-    crc: Crc,
-    burn_out_current_sources: bool,
+    calibration: CalibrationTable,
+    gain_correction: [f32; 8],
+    shadow: ShadowRegisters,
+    gpio: Config4,
+    auto_read: bool,
+    verify_writes: bool,
+    last_counter: Option<u8>,
+    pending_measurement: Option<Mux>,
+    powered_down: bool,
+}
+
+/// ADS122C04 on an I2C bus
+pub type ADS122C04<I2C, DRDY = NoDrdy> = ADS122x04<I2cInterface<I2C>, DRDY>;
+
+/// ADS122U04 on a UART. GPIO and automatic data read mode are only available on this variant.
+pub type ADS122U04<UART, DRDY = NoDrdy> = ADS122x04<SerialInterface<UART>, DRDY>;
+
+impl<BUS> ADS122x04<BUS> {
+    /// Attach the DRDY pin, which is then used to wait for new conversion results instead
+    /// of polling the DRDY bit over the bus. Waiting times out after 10 000 000 pin reads,
+    /// use [`with_drdy_timeout`](Self::with_drdy_timeout) to adjust the limit to the target.
+    pub fn with_drdy<DRDY>(self, drdy: DRDY) -> ADS122x04<BUS, DRDY> {
+        self.with_drdy_timeout(drdy, DRDY_PIN_POLLS)
+    }
+
+    /// Attach the DRDY pin like [`with_drdy`](Self::with_drdy), timing out after the given
+    /// number of pin reads. The time a pin read takes depends on the CPU and GPIO speed, so
+    /// the limit has to be measured on the target to exceed the longest conversion time of
+    /// the used data rates, e.g. about 50 ms at 20 SPS.
+    pub fn with_drdy_timeout<DRDY>(self, drdy: DRDY, polls: u32) -> ADS122x04<BUS, DRDY> {
+        ADS122x04 {
+            bus: self.bus,
+            drdy: Some(drdy),
+            drdy_polls: polls,
+            offset: self.offset,
+            calibration: self.calibration,
+            gain_correction: self.gain_correction,
+            shadow: self.shadow,
+            gpio: self.gpio,
+            auto_read: self.auto_read,
+            verify_writes: self.verify_writes,
+            last_counter: self.last_counter,
+            pending_measurement: self.pending_measurement,
+            powered_down: self.powered_down,
+        }
+    }
+}
+
+impl<BUS, DRDY> ADS122x04<BUS, DRDY> {
+    /// Create a device handler holding the power-on defaults of the device
+    fn with_bus(bus: BUS) -> Self {
+        ADS122x04 {
+            bus,
+            drdy: None,
+            drdy_polls: DRDY_PIN_POLLS,
+            offset: 0,
+            calibration: CalibrationTable::new(),
+            gain_correction: [1.0; 8],
+            shadow: ShadowRegisters::default(),
+            gpio: Config4::default(),
+            auto_read: false,
+            verify_writes: false,
+            last_counter: None,
+            pending_measurement: None,
+            powered_down: false,
+        }
+    }
+
+    /// encodes a specified config register from the cached settings
+    fn encode_reg<E>(&self, reg: u8) -> Result<u8, Error<E>> {
+        match reg {
+            Config3::ADDRESS => Ok(self.shadow.config.config3().to_bits() | self.auto_read as u8),
+            Config4::ADDRESS => Ok(self.gpio.to_bits()),
+            _ => self.shadow.config.register(reg).ok_or(Error::InvalidValue),
+        }
+    }
+
+    /// The cached configuration held in the shadow registers
+    pub fn config(&self) -> &Config {
+        &self.shadow.config
+    }
+
+    /// The shadow copy of the config registers
+    pub fn shadow(&self) -> &ShadowRegisters {
+        &self.shadow
+    }
+
+    /// Enable or disable reading back every written config register. A register that does
+    /// not hold the written value afterwards is reported as [`Error::VerifyMismatch`].
+    pub fn set_verify_writes(&mut self, state: bool) {
+        self.verify_writes = state;
+    }
+
+    /// Check a register value read back after writing `expected`
+    fn check_written<E>(&self, reg: u8, expected: u8, actual: u8) -> Result<(), Error<E>> {
+        let mask = match reg {
+            // the DRDY bit of config register 2 is read-only
+            Config2::ADDRESS => 0x7F,
+            // the data bits of GPIO inputs reflect the pin levels
+            Config4::ADDRESS => 0xF8 | (expected >> 4),
+            _ => 0xFF,
+        };
+        if (expected ^ actual) & mask == 0 {
+            Ok(())
+        } else {
+            Err(Error::VerifyMismatch {
+                register: reg,
+                expected,
+                actual,
+            })
+        }
+    }
+
+    /// transform the raw u32 value to signed i32 value according to datasheet
+    fn raw_to_signed(&self, x: u32) -> i32 {
+        if (x & 0x00800000) == 0x00800000 {
+            (x | 0xFF000000) as i32
+        } else {
+            x as i32
+        }
+    }
+
+    /// Decode the 14-bit left-justified internal temperature sensor result into °C
+    fn raw_to_celsius(&self, x: u32) -> f32 {
+        // the 14-bit two's complement result occupies the upper bits of the 24-bit value
+        let temperature = ((x << 8) as i32) >> 18;
+        temperature as f32 * 0.03125
+    }
+
+    /// Build a sample from a conversion data frame and track the conversion counter
+    fn record_sample(&mut self, (val, counter): (u32, Option<u8>)) -> Sample {
+        let continuity = Continuity::from_counters(self.last_counter, counter);
+        self.last_counter = counter;
+        Sample {
+            raw: self.raw_to_signed(val) - self.active_offset(),
+            counter,
+            continuity,
+            gain: self.shadow.config.gain,
+            v_ref: self.shadow.config.v_ref,
+            gain_correction: self.gain_correction(self.shadow.config.gain),
+        }
+    }
+
+    /// The offset subtracted from conversions with the current settings: the entry of the
+    /// calibration table if there is one, [`offset`](Self::offset) otherwise
+    pub fn active_offset(&self) -> i32 {
+        let key = CalibrationKey::from_config(&self.shadow.config);
+        self.calibration.offset(key).unwrap_or(self.offset)
+    }
+
+    /// The offsets calibrated per gain, PGA bypass and data rate
+    pub fn calibration(&self) -> &CalibrationTable {
+        &self.calibration
+    }
+
+    /// Mutable access to the offset calibration table, e.g. to restore stored offsets
+    pub fn calibration_mut(&mut self) -> &mut CalibrationTable {
+        &mut self.calibration
+    }
+
+    /// The correction factor of the given gain, 1 unless calibrated with
+    /// [`calibrate_gain`](Self::calibrate_gain)
+    pub fn gain_correction(&self, gain: Gain) -> f32 {
+        self.gain_correction[gain as usize]
+    }
+
+    /// Set the correction factor of the given gain, e.g. from a previous calibration. Fails
+    /// with [`CalibrationError::InvalidFactor`] unless the factor is finite and positive.
+    pub fn set_gain_correction(&mut self, gain: Gain, factor: f32) -> Result<(), CalibrationError> {
+        if !is_valid_factor(factor) {
+            return Err(CalibrationError::InvalidFactor);
+        }
+        self.gain_correction[gain as usize] = factor;
+        Ok(())
+    }
+
+    /// The calibration state: offset, calibration table and gain correction factors
+    pub fn calibration_data(&self) -> CalibrationData {
+        CalibrationData {
+            offset: self.offset,
+            table: self.calibration,
+            gain_correction: self.gain_correction,
+        }
+    }
+
+    /// Restore a calibration state, replacing the current one. The current state is kept if
+    /// a gain correction factor is not finite and positive.
+    pub fn set_calibration_data(&mut self, data: &CalibrationData) -> Result<(), CalibrationError> {
+        data.validate()?;
+        self.offset = data.offset;
+        self.calibration = data.table;
+        self.gain_correction = data.gain_correction;
+        Ok(())
+    }
+
+    /// Encode the calibration state into a versioned, CRC-protected blob at the beginning of
+    /// `buffer`, e.g. for storage in flash. Returns the number of bytes written, at most
+    /// [`CalibrationData::MAX_SIZE`].
+    pub fn export_calibration(&self, buffer: &mut [u8]) -> Result<usize, CalibrationError> {
+        self.calibration_data().encode(buffer)
+    }
+
+    /// Restore the calibration state from a blob written by
+    /// [`export_calibration`](Self::export_calibration). The current state is kept if the
+    /// blob is invalid.
+    pub fn import_calibration(&mut self, buffer: &[u8]) -> Result<(), CalibrationError> {
+        let data = CalibrationData::decode(buffer)?;
+        self.set_calibration_data(&data)
+    }
+
+    /// Whether the device has been put in power-down mode and not woken up since
+    pub fn is_powered_down(&self) -> bool {
+        self.powered_down
+    }
+
+    /// Convert the raw ADC value to voltage using the current gain, voltage reference and gain
+    /// correction. Prefer [`Sample::to_voltage`] if the settings may have changed since the
+    /// value was read.
+    pub fn convert_raw_to_voltage(&mut self, raw: i32) -> f32 {
+        // returns voltage in V
+        let gain = self.shadow.config.gain;
+        raw_to_voltage(raw, self.shadow.config.v_ref, gain) * self.gain_correction(gain)
+    }
 }
 
 impl<I2C, E> ADS122x04<I2cInterface<I2C>>
@@ -69,24 +418,12 @@ where
 {
     /// Create a new ADS122C04 device by supplying an I2C address and I2C handler
     pub fn new_i2c(address: u8, i2c: I2C) -> Self {
-        ADS122x04 {
-            bus: I2cInterface { i2c, address },
-            offset: 0,
-            v_ref: VRef::Internal,
-            gain: Gain::Gain1,
-            mux: Mux::Ain0Ain1,
-            current_source: CurrentSource::Off,
-            current_route_1: CurrentRoute::Off,
-            current_route_2: CurrentRoute::Off,
-            data_rate: DataRate::Sps20Normal,
-            pga_bypass: false,
-            turbo_mode: false,
-            conversion_mode: ConversionMode::SingleShot,
-            temperature_sensor_mode: false,
-            data_counter_enable: false,
-            crc: Crc::Disabled,
-            burn_out_current_sources: false,
-        }
+        Self::with_bus(I2cInterface { i2c, address })
+    }
+
+    /// Create a new ADS122C04 device at the address selected by the A1/A0 strapping
+    pub fn new_i2c_with_address(address: Address, i2c: I2C) -> Self {
+        Self::new_i2c(address.into(), i2c)
     }
 }
 
@@ -94,306 +431,514 @@ impl<UART, E> ADS122x04<SerialInterface<UART>>
 where
     UART: Write<Error = E> + Read<Error = E>,
 {
-    /// Create a new ADS122C04 device by supplying a serial handler (UART)
+    /// Create a new ADS122U04 device by supplying a serial handler (UART)
     pub fn new_serial(serial: UART) -> Self {
-        ADS122x04 {
-            bus: SerialInterface { serial },
-            offset: 0,
-            v_ref: VRef::Internal,
-            gain: Gain::Gain1,
-            mux: Mux::Ain0Ain1,
-            current_source: CurrentSource::Off,
-            current_route_1: CurrentRoute::Off,
-            current_route_2: CurrentRoute::Off,
-            data_rate: DataRate::Sps20Normal,
-            pga_bypass: false,
-            turbo_mode: false,
-            conversion_mode: ConversionMode::SingleShot,
-            temperature_sensor_mode: false,
-            data_counter_enable: false,
-            crc: Crc::Disabled,
-            burn_out_current_sources: false,
-        }
+        Self::with_bus(SerialInterface { serial })
     }
 }
 
-impl<BUS, E> ADS122x04<BUS>
+impl<BUS, DRDY, E> ADS122x04<BUS, DRDY>
 where
     BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+    DRDY: InputPin,
 {
     /// updates a specified config register
     fn update_reg(&mut self, reg: u8) -> Result<(), Error<E>> {
-        match reg {
-            0x00 => {
-                let val =
-                    (self.pga_bypass as u8) | ((self.gain as u8) << 1) | ((self.mux as u8) << 4);
-                self.bus.write_register(0x00, val)
-            }
-            0x01 => {
-                let val = (self.temperature_sensor_mode as u8)
-                    | (self.v_ref.to_val() << 1)
-                    | ((self.conversion_mode as u8) << 3)
-                    | ((self.turbo_mode as u8) << 4)
-                    | ((self.data_rate as u8) << 5);
-                self.bus.write_register(0x01, val)
-            }
-            0x02 => {
-                let val = (self.current_source as u8)
-                    | ((self.burn_out_current_sources as u8) << 3)
-                    | ((self.crc as u8) << 4)
-                    | ((self.data_counter_enable as u8) << 6);
-                self.bus.write_register(0x02, val)
+        let val = self.encode_reg(reg)?;
+        self.shadow.mark_dirty(reg);
+        self.bus.write_register(reg, val)?;
+        if self.verify_writes {
+            let actual = self.read_reg(reg)?;
+            self.check_written(reg, val, actual)?;
+        }
+        self.shadow.mark_clean(reg);
+        Ok(())
+    }
+
+    /// Write all dirty shadow registers to the device
+    pub fn flush(&mut self) -> Result<(), Error<E>> {
+        // config register 2 goes first, it selects the CRC mode used to read back the others
+        for reg in [
+            Config2::ADDRESS,
+            Config0::ADDRESS,
+            Config1::ADDRESS,
+            Config3::ADDRESS,
+            Config4::ADDRESS,
+        ] {
+            if self.shadow.is_dirty(reg) {
+                self.update_reg(reg)?;
             }
-            0x03 => {
-                let val = ((self.current_route_2 as u8) << 2) | ((self.current_route_1 as u8) << 5);
-                self.bus.write_register(0x03, val)
+        }
+        Ok(())
+    }
+
+    /// Read all config registers from the device into the shadow registers, discarding
+    /// unwritten changes. The CRC mode is taken from the device, so that this also recovers
+    /// from a reset of the device, e.g. a brown-out, while CRC was enabled.
+    pub fn resync(&mut self) -> Result<(), Error<E>> {
+        // a device that has been reset answers without the integrity bytes of the CRC mode
+        let config2 = match self.read_reg(Config2::ADDRESS) {
+            Err(Error::CrcMismatch | Error::Timeout) => {
+                self.bus.read_register(Config2::ADDRESS, Crc::Disabled)?
             }
-            _ => Err(Error::InvalidValue),
+            result => result?,
+        };
+        self.shadow.config.crc = Config2::from_bits(config2)?.crc;
+        let config = self.read_config()?;
+        if config.data_counter_enable != self.shadow.config.data_counter_enable {
+            self.last_counter = None;
+        }
+        self.shadow = ShadowRegisters::new(config);
+        if <BUS::Variant as Variant>::LAST_REGISTER >= Config4::ADDRESS {
+            self.gpio = Config4::from_bits(self.read_reg(Config4::ADDRESS)?);
+        }
+        Ok(())
+    }
+
+    /// Rewrite all shadow registers to the device, e.g. after a brown-out reset it
+    pub fn restore(&mut self) -> Result<(), Error<E>> {
+        for reg in Config::ADDRESSES {
+            self.shadow.mark_dirty(reg);
+        }
+        if <BUS::Variant as Variant>::LAST_REGISTER >= Config4::ADDRESS {
+            self.shadow.mark_dirty(Config4::ADDRESS);
         }
+        self.flush()
     }
 
     /// reads a specified config register
     fn read_reg(&mut self, reg: u8) -> Result<u8, Error<E>> {
-        match reg {
-            0x00 => self.bus.read_register(0x00),
-            0x01 => self.bus.read_register(0x01),
-            0x02 => self.bus.read_register(0x02),
-            0x03 => self.bus.read_register(0x03),
-            _ => Err(Error::InvalidValue),
+        if reg <= <BUS::Variant as Variant>::LAST_REGISTER {
+            self.bus.read_register(reg, self.shadow.config.crc)
+        } else {
+            Err(Error::InvalidValue)
+        }
+    }
+
+    /// Apply a whole configuration, writing only the config registers that change
+    pub fn apply_config(&mut self, config: &Config) -> Result<(), Error<E>> {
+        let previous = core::mem::replace(&mut self.shadow.config, *config);
+        if previous.data_counter_enable != config.data_counter_enable {
+            self.last_counter = None;
+        }
+        for reg in Config::ADDRESSES {
+            if previous.register(reg) != config.register(reg) {
+                self.shadow.mark_dirty(reg);
+            }
+        }
+        self.flush()
+    }
+
+    /// Read and decode all config registers from the device. The voltage of an external or
+    /// analog supply reference cannot be read back and is taken from the cached configuration.
+    pub fn read_config(&mut self) -> Result<Config, Error<E>> {
+        let mut registers = [0; 4];
+        for (reg, val) in Config::ADDRESSES.into_iter().zip(registers.iter_mut()) {
+            *val = self.read_reg(reg)?;
+        }
+        Ok(Config::from_registers(
+            registers,
+            self.shadow.config.v_ref.to_voltage(),
+        )?)
+    }
+
+    /// Apply `config` and take a single conversion of a sensor. A result at the limits of the
+    /// input range, before the offset is subtracted, is reported as [`Error::OpenInput`] or
+    /// [`Error::ShortedInput`] according to `limits`.
+    pub(crate) fn measure_sensor(
+        &mut self,
+        config: &Config,
+        limits: SensorLimits,
+    ) -> Result<Sample, Error<E>> {
+        self.apply_config(config)?;
+        self.start()?;
+        self.wait_for_data()?;
+        let sample = self.read_sample()?;
+        let raw = sample.raw + self.active_offset();
+        let full_scale = raw >= 0x7F_FFFF;
+        let not_positive = sample.raw <= 0;
+        let (open, shorted) = match limits {
+            SensorLimits::OpenHigh => (full_scale, not_positive),
+            SensorLimits::OpenLow => (not_positive, full_scale),
+            SensorLimits::Bipolar => (full_scale || raw <= -0x80_0000, false),
+        };
+        if open {
+            return Err(Error::OpenInput);
+        }
+        if shorted {
+            return Err(Error::ShortedInput);
+        }
+        Ok(sample)
+    }
+
+    /// Average a number of single conversions of the raw ADC value with the offset subtracted
+    fn average_raw_adc(&mut self, count: i32) -> Result<i32, Error<E>> {
+        let mut sum = 0;
+        for _ in 0..count {
+            self.start()?;
+            self.wait_for_data()?;
+            sum += self.get_raw_adc()?;
         }
+        Ok(sum / count)
     }
 
-    /// Calibrate the offset (according to 8.3.11 Offset Calibration in datasheet)
-    /// This is recommended upon startup and after changing the gain.
+    /// Calibrate the offset (according to 8.3.11 Offset Calibration in datasheet) at the
+    /// current gain, PGA bypass and data rate. The result is stored in the calibration table
+    /// and subtracted whenever these settings are active again, while the fallback
+    /// [`offset`](Self::offset) for uncalibrated settings is left unchanged. This is
+    /// recommended upon startup for each used setting. Fails with
+    /// [`CalibrationError::TableFull`] if the setting has no offset yet and the table is full.
     pub fn calibrate_offset(&mut self) -> Result<(), Error<E>> {
-        const NUM_AVG: usize = 10;
-        let timeout = 1000;
+        const NUM_AVG: i32 = 10;
         // short the inputs to mid-supply (AVDD + AVSS) / 2
-        let previous_mux = self.mux;
+        let previous_mux = self.shadow.config.mux;
+        let previous_mode = self.shadow.config.conversion_mode;
         self.set_input_mux(Mux::Shorted)?;
-        self.set_data_rate(DataRate::Sps40Turbo)?;
         self.set_conversion_mode(ConversionMode::SingleShot)?;
-        // reset offset
-        self.offset = 0;
-        // take multiple readings and average
-        let mut offset = 0;
-        let mut timeout_counter = 0;
-        for _ in 0..NUM_AVG {
-            self.start()?;
-            while !self.get_data_ready()? {
-                timeout_counter += 1;
-                if timeout_counter > timeout {
-                    return Err(Error::Timeout);
-                }
-            }
-            offset += self.get_raw_adc()?;
-        }
-        // store offset
-        self.offset = offset / (NUM_AVG as i32);
-        // return to previous mux
+        // take multiple readings and average, adding back the offset subtracted from them
+        let key = CalibrationKey::from_config(&self.shadow.config);
+        let offset = self.active_offset() + self.average_raw_adc(NUM_AVG)?;
+        // return to previous mux and conversion mode
         self.set_input_mux(previous_mux)?;
-        Ok(())
+        self.set_conversion_mode(previous_mode)?;
+        // store offset
+        Ok(self.calibration.set_offset(key, offset)?)
+    }
+
+    /// Calibrate the gain error at the current gain by measuring a known voltage in V applied
+    /// to the selected input. The correction factor is stored for the current gain, applied
+    /// to all following voltages and returned. The offset should be calibrated first.
+    pub fn calibrate_gain(&mut self, reference_voltage: f32) -> Result<f32, Error<E>> {
+        const NUM_AVG: i32 = 10;
+        let raw = self.average_raw_adc(NUM_AVG)?;
+        let gain = self.shadow.config.gain;
+        let measured = raw_to_voltage(raw, self.shadow.config.v_ref, gain);
+        let factor = reference_voltage / measured;
+        if !is_valid_factor(factor) {
+            return Err(Error::InvalidValue);
+        }
+        self.gain_correction[gain as usize] = factor;
+        Ok(factor)
+    }
+
+    /// Two-point calibration: calibrate the offset with shorted inputs, then the gain with a
+    /// known voltage in V applied to the selected input. Returns the gain correction factor.
+    pub fn calibrate_offset_and_gain(&mut self, reference_voltage: f32) -> Result<f32, Error<E>> {
+        self.calibrate_offset()?;
+        self.calibrate_gain(reference_voltage)
     }
 
     /// Enable or disable the programmable gain amplifier (PGA)
     pub fn set_pga_bypass(&mut self, state: bool) -> Result<(), Error<E>> {
-        self.pga_bypass = state;
-        self.update_reg(0x00)
+        self.shadow.config.pga_bypass = state;
+        self.update_reg(Config0::ADDRESS)
     }
 
     /// Read the status of the programmable gain amplifier (PGA)
     pub fn get_pga_bypass(&mut self) -> Result<bool, Error<E>> {
-        self.read_reg(0x00).map(|val| (val & 0b1) == 1)
+        Ok(Config0::from_bits(self.read_reg(Config0::ADDRESS)?)?.pga_bypass)
     }
 
     /// Set the gain as either 0, 1, 2, 4, 8, 16, 32, 64 or 128
     pub fn set_gain(&mut self, gain: Gain) -> Result<(), Error<E>> {
-        self.gain = gain;
-        self.update_reg(0x00)
+        self.shadow.config.gain = gain;
+        self.update_reg(Config0::ADDRESS)
     }
 
     /// Read the gain value
     pub fn get_gain(&mut self) -> Result<Gain, Error<E>> {
-        self.read_reg(0x00)
-            .map(|val| Gain::from((val >> 1) & 0b111))
+        Ok(Config0::from_bits(self.read_reg(Config0::ADDRESS)?)?.gain)
     }
 
     /// Set the input multiplexer (MUX)
     pub fn set_input_mux(&mut self, mux: Mux) -> Result<(), Error<E>> {
-        self.mux = mux;
-        self.update_reg(0x00)
+        self.shadow.config.mux = mux;
+        self.update_reg(Config0::ADDRESS)
     }
 
     /// Read the input multiplexer (MUX) setting
-    pub fn get_input_mux(&mut self) -> Result<u8, Error<E>> {
-        self.read_reg(0x00).map(|val| val >> 4)
+    pub fn get_input_mux(&mut self) -> Result<Mux, Error<E>> {
+        Ok(Config0::from_bits(self.read_reg(Config0::ADDRESS)?)?.mux)
     }
 
     /// Enable or disable temperature sensor mode (TS)
     pub fn set_temperature_sensor_mode(&mut self, state: bool) -> Result<(), Error<E>> {
-        self.temperature_sensor_mode = state;
-        self.update_reg(0x01)
+        self.shadow.config.temperature_sensor_mode = state;
+        self.update_reg(Config1::ADDRESS)
     }
 
     /// Read the temperature sensor mode (TS)
     pub fn get_temperature_sensor_mode(&mut self) -> Result<bool, Error<E>> {
-        self.read_reg(0x01).map(|val| (val & 0b1) == 1)
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS)?)?.temperature_sensor_mode)
     }
 
     /// Set the voltage reference (VREF)
     pub fn set_vref(&mut self, v_ref: VRef) -> Result<(), Error<E>> {
-        self.v_ref = v_ref;
-        self.update_reg(0x01)
+        self.shadow.config.v_ref = v_ref;
+        self.update_reg(Config1::ADDRESS)
     }
 
     /// Read the voltage reference (VREF)
     pub fn get_vref(&mut self) -> Result<VRef, Error<E>> {
-        self.read_reg(0x01)
-            .map(|val| VRef::from((val >> 1) & 0b11, self.v_ref.to_voltage()))
+        let config = Config1::from_bits(self.read_reg(Config1::ADDRESS)?)?;
+        Ok(config
+            .v_ref
+            .with_voltage(self.shadow.config.v_ref.to_voltage()))
     }
 
     /// Set the conversion mode (CM)
     pub fn set_conversion_mode(&mut self, mode: ConversionMode) -> Result<(), Error<E>> {
-        self.conversion_mode = mode;
-        self.update_reg(0x01)
+        self.shadow.config.conversion_mode = mode;
+        self.update_reg(Config1::ADDRESS)
     }
 
     /// Read the conversion mode (CM)
     pub fn get_conversion_mode(&mut self) -> Result<ConversionMode, Error<E>> {
-        self.read_reg(0x01)
-            .map(|val| ConversionMode::from((val >> 3) & 0b1))
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS)?)?.conversion_mode)
     }
 
     /// Read the operating mode
     pub fn get_operating_mode(&mut self) -> Result<bool, Error<E>> {
-        self.read_reg(0x01).map(|val| ((val >> 4) & 0b1) == 1)
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS)?)?.turbo_mode())
     }
 
     /// Set the data rate
     pub fn set_data_rate(&mut self, rate: DataRate) -> Result<(), Error<E>> {
-        self.data_rate = rate;
-        self.turbo_mode = (self.data_rate as u8 & 0b1) == 1;
-        self.update_reg(0x01)
+        self.shadow.config.data_rate = rate;
+        self.update_reg(Config1::ADDRESS)
     }
 
     /// Read the data rate
     pub fn get_data_rate(&mut self) -> Result<DataRate, Error<E>> {
-        self.read_reg(0x01)
-            .map(|val| DataRate::from((val >> 4) & 0b1111))
+        Ok(Config1::from_bits(self.read_reg(Config1::ADDRESS)?)?.data_rate)
     }
 
     /// Set the current level of the internal excitation current sources
     pub fn set_current_level(&mut self, current: CurrentSource) -> Result<(), Error<E>> {
-        self.current_source = current;
-        self.update_reg(0x02)
+        self.shadow.config.current_source = current;
+        self.update_reg(Config2::ADDRESS)
     }
 
     /// Read the current level of the internal excitation current sources
     pub fn get_current_level(&mut self) -> Result<CurrentSource, Error<E>> {
-        self.read_reg(0x02)
-            .map(|val| CurrentSource::from(val & 0b111))
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS)?)?.current_source)
     }
 
     /// Enable or disable the 10 uA burnout current sources
     pub fn set_burnout_current_source(&mut self, state: bool) -> Result<(), Error<E>> {
-        self.burn_out_current_sources = state;
-        self.update_reg(0x02)
+        self.shadow.config.burn_out_current_sources = state;
+        self.update_reg(Config2::ADDRESS)
     }
 
     /// Read the state of the 10 uA burnout current sources
     pub fn get_burnout_current_source(&mut self) -> Result<bool, Error<E>> {
-        self.read_reg(0x02).map(|val| ((val >> 3) & 0b1) == 1)
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS)?)?.burn_out_current_sources)
     }
 
-    /// Set the CRC mode
+    /// Set the CRC mode. Subsequent conversion and register reads are checked against the
+    /// integrity bytes sent by the device and fail with [`Error::CrcMismatch`] on corruption.
     pub fn set_crc(&mut self, crc: Crc) -> Result<(), Error<E>> {
-        self.crc = crc;
-        self.update_reg(0x02)
+        self.shadow.config.crc = crc;
+        self.update_reg(Config2::ADDRESS)
     }
 
     /// Read the CRC mode
     pub fn get_crc(&mut self) -> Result<Crc, Error<E>> {
-        self.read_reg(0x02).map(|val| Crc::from((val >> 4) & 0b11))
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS)?)?.crc)
     }
 
     /// Enable or disable data counter
     pub fn set_data_counter(&mut self, state: bool) -> Result<(), Error<E>> {
-        self.data_counter_enable = state;
-        self.update_reg(0x02)
+        self.shadow.config.data_counter_enable = state;
+        self.last_counter = None;
+        self.update_reg(Config2::ADDRESS)
     }
 
     /// Read the state of the data counter
     pub fn get_data_counter(&mut self) -> Result<bool, Error<E>> {
-        self.read_reg(0x02).map(|val| ((val >> 6) & 0b1) == 1)
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS)?)?.data_counter)
     }
 
     /// Read the data ready (DRDY) register
     pub fn get_data_ready(&mut self) -> Result<bool, Error<E>> {
-        self.read_reg(0x02).map(|val| ((val >> 7) & 0b1) == 1)
+        Ok(Config2::from_bits(self.read_reg(Config2::ADDRESS)?)?.data_ready)
+    }
+
+    /// Check whether a new conversion result is available, on the DRDY pin if one is attached
+    /// or on the DRDY bit otherwise
+    fn data_ready(&mut self) -> Result<bool, Error<E>> {
+        match self.drdy.as_mut() {
+            Some(drdy) => drdy.is_low().map_err(|_| Error::PinError),
+            None => self.get_data_ready(),
+        }
+    }
+
+    /// Read the raw ADC value and subtract the offset, returning `WouldBlock` until a new
+    /// conversion result is available
+    pub fn read_nb(&mut self) -> nb::Result<i32, Error<E>> {
+        if !self.data_ready()? {
+            return Err(nb::Error::WouldBlock);
+        }
+        Ok(self.get_raw_adc()?)
+    }
+
+    /// Perform a single-shot measurement on the given input multiplexer setting.
+    /// The first call selects the input and issues START/SYNC, subsequent calls return
+    /// `WouldBlock` until the conversion result is available. Requesting a different input
+    /// while a measurement is pending restarts the measurement.
+    ///
+    /// A device in continuous conversion mode is switched to single-shot mode, which is kept
+    /// afterwards, as is the selected input. Call
+    /// [`set_conversion_mode`](Self::set_conversion_mode) to return to continuous conversions.
+    pub fn measure_nb(&mut self, mux: Mux) -> nb::Result<i32, Error<E>> {
+        if self.pending_measurement != Some(mux) {
+            if let ConversionMode::Continuous = self.shadow.config.conversion_mode {
+                self.set_conversion_mode(ConversionMode::SingleShot)?;
+            }
+            self.set_input_mux(mux)?;
+            self.start()?;
+            self.pending_measurement = Some(mux);
+            return Err(nb::Error::WouldBlock);
+        }
+        let raw = self.read_nb()?;
+        self.pending_measurement = None;
+        Ok(raw)
+    }
+
+    /// Wait until a new conversion result is available. With a DRDY pin attached this blocks
+    /// until the pin goes low, otherwise the DRDY bit is polled over the bus. Either way
+    /// [`Error::Timeout`] is returned if no conversion finishes, e.g. because the device has
+    /// been powered down.
+    pub fn wait_for_data(&mut self) -> Result<(), Error<E>> {
+        if let Some(drdy) = self.drdy.as_mut() {
+            let mut timeout_counter = 0;
+            while drdy.is_high().map_err(|_| Error::PinError)? {
+                timeout_counter += 1;
+                if timeout_counter > self.drdy_polls {
+                    return Err(Error::Timeout);
+                }
+            }
+            return Ok(());
+        }
+        let mut timeout_counter = 0;
+        while !self.get_data_ready()? {
+            timeout_counter += 1;
+            if timeout_counter > DRDY_BIT_POLLS {
+                return Err(Error::Timeout);
+            }
+        }
+        Ok(())
     }
 
     /// Set the current routing of the excitation current source 1
     pub fn set_current_route_1(&mut self, route: CurrentRoute) -> Result<(), Error<E>> {
-        self.current_route_1 = route;
-        self.update_reg(0x03)
+        self.shadow.config.current_route_1 = route;
+        self.update_reg(Config3::ADDRESS)
     }
 
     /// Read the current routing of the excitation current source 1
     pub fn get_current_route_1(&mut self) -> Result<CurrentRoute, Error<E>> {
-        self.read_reg(0x03)
-            .map(|val| CurrentRoute::from((val >> 5) & 0b111))
+        Ok(Config3::from_bits(self.read_reg(Config3::ADDRESS)?)?.current_route_1)
     }
 
     /// Set the current routing of the excitation current source 2
     pub fn set_current_route_2(&mut self, route: CurrentRoute) -> Result<(), Error<E>> {
-        self.current_route_2 = route;
-        self.update_reg(0x03)
+        self.shadow.config.current_route_2 = route;
+        self.update_reg(Config3::ADDRESS)
     }
 
     /// Read the current routing of the excitation current source 2
     pub fn get_current_route_2(&mut self) -> Result<CurrentRoute, Error<E>> {
-        self.read_reg(0x03)
-            .map(|val| CurrentRoute::from((val >> 3) & 0b111))
+        Ok(Config3::from_bits(self.read_reg(Config3::ADDRESS)?)?.current_route_2)
     }
 
-    /// transform the raw u32 value to signed i32 value according to datasheet
-    fn raw_to_signed(&self, x: u32) -> i32 {
-        if (x & 0x00800000) == 0x00800000 {
-            (x | 0xFF000000) as i32
-        } else {
-            x as i32
-        }
+    /// Read a conversion result including the conversion counter if it is enabled.
+    /// In continuous mode, [`Sample::continuity`] reveals skipped or repeated conversions.
+    pub fn read_sample(&mut self) -> Result<Sample, Error<E>> {
+        let frame = self.bus.read_data(
+            self.shadow.config.crc,
+            self.shadow.config.data_counter_enable,
+        )?;
+        Ok(self.record_sample(frame))
     }
 
     /// Read the raw ADC value and subtract the offset
     pub fn get_raw_adc(&mut self) -> Result<i32, Error<E>> {
-        self.bus
-            .read_data()
-            .map(|val| self.raw_to_signed(val) - self.offset)
+        self.read_sample().map(|sample| sample.raw)
     }
 
-    /// Read the voltage of the ADC
-    pub fn get_voltage(&mut self) -> Result<f32, Error<E>> {
-        // returns voltage in V
-        let raw = self.get_raw_adc();
-        let v_ref = self.v_ref.to_voltage();
-        raw.map(|raw| (v_ref as f64 / ((1 << 23) as f64) * (raw as f64)) as f32)
+    /// Measure the internal temperature sensor in °C. Temperature sensor mode is enabled for
+    /// the measurement and the previous mode is restored afterwards, also if the measurement
+    /// fails.
+    pub fn get_temperature_celsius(&mut self) -> Result<f32, Error<E>> {
+        let previous_mode = self.shadow.config.temperature_sensor_mode;
+        let result = self.read_temperature(previous_mode);
+        let restored = if previous_mode {
+            Ok(())
+        } else {
+            self.set_temperature_sensor_mode(false)
+        };
+        let val = result?;
+        restored?;
+        Ok(self.raw_to_celsius(val))
+    }
+
+    /// Enable temperature sensor mode unless it is `enabled` already, take a conversion and
+    /// read its raw value
+    fn read_temperature(&mut self, enabled: bool) -> Result<u32, Error<E>> {
+        if !enabled {
+            self.set_temperature_sensor_mode(true)?;
+        }
+        self.start()?;
+        self.wait_for_data()?;
+        let (val, counter) = self.bus.read_data(
+            self.shadow.config.crc,
+            self.shadow.config.data_counter_enable,
+        )?;
+        self.last_counter = counter;
+        Ok(val)
     }
 
-    /// Convert the raw ADC value to voltage
-    pub fn convert_raw_to_voltage(&mut self, raw: i32) -> f32 {
+    /// Read the voltage of the ADC
+    pub fn get_voltage(&mut self) -> Result<f32, Error<E>> {
         // returns voltage in V
-        let v_ref = self.v_ref.to_voltage();
-        (v_ref as f64 / ((1 << 23) as f64) * (raw as f64)) as f32
+        self.read_sample().map(|sample| sample.to_voltage())
     }
 
-    /// Reset the device
+    /// Reset the device, the shadow registers return to the power-on defaults
     pub fn reset(&mut self) -> Result<(), Error<E>> {
-        self.bus.write_data(Commands::Reset as u8)
+        self.last_counter = None;
+        self.pending_measurement = None;
+        self.bus.write_data(Commands::Reset as u8)?;
+        self.shadow = ShadowRegisters::default();
+        self.gpio = Config4::default();
+        self.auto_read = false;
+        self.powered_down = false;
+        Ok(())
     }
 
-    /// Start a measurement
+    /// Start a measurement, this also wakes the device from power-down mode
     pub fn start(&mut self) -> Result<(), Error<E>> {
-        self.bus.write_data(Commands::StartSync as u8)
+        self.bus.write_data(Commands::StartSync as u8)?;
+        self.powered_down = false;
+        Ok(())
+    }
+
+    /// Put the device in power-down mode, the configuration registers are retained.
+    /// The next call to [`start`](Self::start) wakes it up again.
+    pub fn power_down(&mut self) -> Result<(), Error<E>> {
+        self.bus.write_data(Commands::PowerDown as u8)?;
+        self.powered_down = true;
+        Ok(())
+    }
+
+    /// Wake the device with START/SYNC, wait for the conversion result and power down again.
+    /// Intended for duty-cycled sampling in single-shot conversion mode.
+    pub fn sample_and_sleep(&mut self) -> Result<Sample, Error<E>> {
+        self.start()?;
+        self.wait_for_data()?;
+        let sample = self.read_sample()?;
+        self.power_down()?;
+        Ok(sample)
     }
 }
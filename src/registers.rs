@@ -1,4 +1,9 @@
 //! ADS122x04 registers and commands
+
+/// A register field holds a reserved or invalid bit pattern
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct DecodeError(pub u8);
+
 #[derive(Debug, Copy, Clone)]
 #[allow(dead_code)]
 /// Commands to send to the device
@@ -17,7 +22,7 @@ pub enum Commands {
     WReg = 0b1000000,
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code, missing_docs)]
 pub enum Mux {
     Ain0Ain1 = 0b0000,
@@ -37,7 +42,33 @@ pub enum Mux {
     Shorted = 0b1110,
 }
 
-#[derive(Debug, Copy, Clone)]
+impl TryFrom<u8> for Mux {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
+        match val {
+            0b0000 => Ok(Self::Ain0Ain1),
+            0b0001 => Ok(Self::Ain0Ain2),
+            0b0010 => Ok(Self::Ain0Ain3),
+            0b0011 => Ok(Self::Ain1Ain0),
+            0b0100 => Ok(Self::Ain1Ain2),
+            0b0101 => Ok(Self::Ain1Ain3),
+            0b0110 => Ok(Self::Ain2Ain3),
+            0b0111 => Ok(Self::Ain3Ain2),
+            0b1000 => Ok(Self::Ain0Avss),
+            0b1001 => Ok(Self::Ain1Avss),
+            0b1010 => Ok(Self::Ain2Avss),
+            0b1011 => Ok(Self::Ain3Avss),
+            0b1100 => Ok(Self::VrefMonitor),
+            0b1101 => Ok(Self::AvddMonitor),
+            0b1110 => Ok(Self::Shorted),
+            _ => Err(DecodeError(val)),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code, missing_docs)]
 pub enum DataRate {
     Sps20Normal = 0b0000,
@@ -56,30 +87,32 @@ pub enum DataRate {
     Sps2000Turbo = 0b1101,
 }
 
-#[allow(dead_code, missing_docs)]
-impl DataRate {
-    pub fn from(val: u8) -> Self {
+impl TryFrom<u8> for DataRate {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0b0000 => Self::Sps20Normal,
-            0b0010 => Self::Sps45Normal,
-            0b0100 => Self::Sps90Normal,
-            0b0110 => Self::Sps175Normal,
-            0b1000 => Self::Sps330Normal,
-            0b1010 => Self::Sps600Normal,
-            0b1100 => Self::Sps1000Normal,
-            0b0001 => Self::Sps40Turbo,
-            0b0011 => Self::Sps90Turbo,
-            0b0101 => Self::Sps180Turbo,
-            0b0111 => Self::Sps350Turbo,
-            0b1001 => Self::Sps660Turbo,
-            0b1011 => Self::Sps1200Turbo,
-            0b1101 => Self::Sps2000Turbo,
-            _ => Self::Sps20Normal,
+            0b0000 => Ok(Self::Sps20Normal),
+            0b0010 => Ok(Self::Sps45Normal),
+            0b0100 => Ok(Self::Sps90Normal),
+            0b0110 => Ok(Self::Sps175Normal),
+            0b1000 => Ok(Self::Sps330Normal),
+            0b1010 => Ok(Self::Sps600Normal),
+            0b1100 => Ok(Self::Sps1000Normal),
+            0b0001 => Ok(Self::Sps40Turbo),
+            0b0011 => Ok(Self::Sps90Turbo),
+            0b0101 => Ok(Self::Sps180Turbo),
+            0b0111 => Ok(Self::Sps350Turbo),
+            0b1001 => Ok(Self::Sps660Turbo),
+            0b1011 => Ok(Self::Sps1200Turbo),
+            0b1101 => Ok(Self::Sps2000Turbo),
+            _ => Err(DecodeError(val)),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(dead_code, missing_docs)]
 pub enum Gain {
     Gain1 = 0b000,
@@ -92,24 +125,32 @@ pub enum Gain {
     Gain128 = 0b111,
 }
 
-#[allow(dead_code, missing_docs)]
-impl Gain {
-    pub fn from(val: u8) -> Self {
+impl TryFrom<u8> for Gain {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0b000 => Self::Gain1,
-            0b001 => Self::Gain2,
-            0b010 => Self::Gain4,
-            0b011 => Self::Gain8,
-            0b100 => Self::Gain16,
-            0b101 => Self::Gain32,
-            0b110 => Self::Gain64,
-            0b111 => Self::Gain128,
-            _ => Self::Gain1,
+            0b000 => Ok(Self::Gain1),
+            0b001 => Ok(Self::Gain2),
+            0b010 => Ok(Self::Gain4),
+            0b011 => Ok(Self::Gain8),
+            0b100 => Ok(Self::Gain16),
+            0b101 => Ok(Self::Gain32),
+            0b110 => Ok(Self::Gain64),
+            0b111 => Ok(Self::Gain128),
+            _ => Err(DecodeError(val)),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[allow(dead_code, missing_docs)]
+impl Gain {
+    pub fn to_factor(&self) -> f32 {
+        (1 << (*self as u8)) as f32
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code, missing_docs)]
 pub enum CurrentSource {
     Off = 0b000,
@@ -122,21 +163,26 @@ pub enum CurrentSource {
     I1500uA = 0b111,
 }
 
-#[allow(dead_code, missing_docs)]
-impl CurrentSource {
-    pub fn from(val: u8) -> Self {
+impl TryFrom<u8> for CurrentSource {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0b001 => Self::I10uA,
-            0b010 => Self::I50uA,
-            0b011 => Self::I100uA,
-            0b100 => Self::I250uA,
-            0b101 => Self::I500uA,
-            0b110 => Self::I1000uA,
-            0b111 => Self::I1500uA,
-            _ => Self::Off,
+            0b000 => Ok(Self::Off),
+            0b001 => Ok(Self::I10uA),
+            0b010 => Ok(Self::I50uA),
+            0b011 => Ok(Self::I100uA),
+            0b100 => Ok(Self::I250uA),
+            0b101 => Ok(Self::I500uA),
+            0b110 => Ok(Self::I1000uA),
+            0b111 => Ok(Self::I1500uA),
+            _ => Err(DecodeError(val)),
         }
     }
+}
 
+#[allow(dead_code, missing_docs)]
+impl CurrentSource {
     pub fn to_amps(&self) -> f32 {
         match self {
             CurrentSource::Off => { 0.0 }
@@ -151,7 +197,7 @@ impl CurrentSource {
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code, missing_docs)]
 pub enum CurrentRoute {
     Off = 0b000,
@@ -163,39 +209,43 @@ pub enum CurrentRoute {
     RefN = 0b110,
 }
 
-#[allow(dead_code, missing_docs)]
-impl CurrentRoute {
-    pub fn from(val: u8) -> Self {
+impl TryFrom<u8> for CurrentRoute {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0b001 => Self::Ain0,
-            0b010 => Self::Ain1,
-            0b011 => Self::Ain2,
-            0b100 => Self::Ain3,
-            0b101 => Self::RefP,
-            0b110 => Self::RefN,
-            _ => Self::Off,
+            0b000 => Ok(Self::Off),
+            0b001 => Ok(Self::Ain0),
+            0b010 => Ok(Self::Ain1),
+            0b011 => Ok(Self::Ain2),
+            0b100 => Ok(Self::Ain3),
+            0b101 => Ok(Self::RefP),
+            0b110 => Ok(Self::RefN),
+            _ => Err(DecodeError(val)),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code, missing_docs)]
 pub enum ConversionMode {
     SingleShot = 0,
     Continuous = 1,
 }
 
-#[allow(dead_code, missing_docs)]
-impl ConversionMode {
-    pub fn from(val: u8) -> Self {
+impl TryFrom<u8> for ConversionMode {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0 => Self::SingleShot,
-            _ => Self::Continuous,
+            0 => Ok(Self::SingleShot),
+            1 => Ok(Self::Continuous),
+            _ => Err(DecodeError(val)),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 #[allow(dead_code, missing_docs)]
 pub enum Crc {
     Disabled = 0b00,
@@ -203,18 +253,20 @@ pub enum Crc {
     Crc16 = 0b10,
 }
 
-#[allow(dead_code, missing_docs)]
-impl Crc {
-    pub fn from(val: u8) -> Self {
+impl TryFrom<u8> for Crc {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0b01 => Self::Inverted,
-            0b10 => Self::Crc16,
-            _ => Self::Disabled,
+            0b00 => Ok(Self::Disabled),
+            0b01 => Ok(Self::Inverted),
+            0b10 => Ok(Self::Crc16),
+            _ => Err(DecodeError(val)),
         }
     }
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 #[allow(dead_code)]
 /// Voltage reference
 pub enum VRef {
@@ -229,10 +281,14 @@ pub enum VRef {
 #[allow(dead_code, missing_docs)]
 impl VRef {
     pub fn to_val(&self) -> u8 {
+        self.source() as u8
+    }
+
+    pub fn source(&self) -> VRefSource {
         match self {
-            VRef::Internal => 0b00,
-            VRef::External(_) => 0b01,
-            VRef::AnalogSupply(_) => 0b10,
+            VRef::Internal => VRefSource::Internal,
+            VRef::External(_) => VRefSource::External,
+            VRef::AnalogSupply(_) => VRefSource::AnalogSupply,
         }
     }
 
@@ -243,13 +299,386 @@ impl VRef {
             VRef::AnalogSupply(v) => *v,
         }
     }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+#[allow(dead_code)]
+/// Voltage reference selection as stored in the VREF bits of config register 1
+pub enum VRefSource {
+    /// internal 2.048 V reference
+    Internal = 0b00,
+    /// external reference on RefP and RefN pins
+    External = 0b01,
+    /// AVDD-AVSS as a reference
+    AnalogSupply = 0b10,
+}
 
-    pub fn from(val: u8, voltage: f32) -> Self {
+impl TryFrom<u8> for VRefSource {
+    type Error = DecodeError;
+
+    fn try_from(val: u8) -> Result<Self, Self::Error> {
         match val {
-            0b00 => VRef::Internal,
-            0b01 => VRef::External(voltage),
-            0b10 => VRef::AnalogSupply(voltage),
-            _ => VRef::Internal,
+            0b00 => Ok(Self::Internal),
+            0b01 => Ok(Self::External),
+            0b10 | 0b11 => Ok(Self::AnalogSupply),
+            _ => Err(DecodeError(val)),
         }
     }
-}
\ No newline at end of file
+}
+
+#[allow(dead_code, missing_docs)]
+impl VRefSource {
+    pub fn with_voltage(&self, voltage: f32) -> VRef {
+        match self {
+            VRefSource::Internal => VRef::Internal,
+            VRefSource::External => VRef::External(voltage),
+            VRefSource::AnalogSupply => VRef::AnalogSupply(voltage),
+        }
+    }
+}
+
+/// Configuration register 0 (00h)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Config0 {
+    /// input multiplexer (MUX)
+    pub mux: Mux,
+    /// gain of the PGA (GAIN)
+    pub gain: Gain,
+    /// bypass the PGA (PGA_BYPASS)
+    pub pga_bypass: bool,
+}
+
+impl Config0 {
+    /// Register address
+    pub const ADDRESS: u8 = 0x00;
+
+    /// Encode the register value
+    pub fn to_bits(&self) -> u8 {
+        ((self.mux as u8) << 4) | ((self.gain as u8) << 1) | (self.pga_bypass as u8)
+    }
+
+    /// Decode the register value, failing on reserved bit patterns
+    pub fn from_bits(val: u8) -> Result<Self, DecodeError> {
+        Ok(Config0 {
+            mux: Mux::try_from(val >> 4)?,
+            gain: Gain::try_from((val >> 1) & 0b111)?,
+            pga_bypass: (val & 0b1) == 1,
+        })
+    }
+}
+
+impl Default for Config0 {
+    fn default() -> Self {
+        Config0 {
+            mux: Mux::Ain0Ain1,
+            gain: Gain::Gain1,
+            pga_bypass: false,
+        }
+    }
+}
+
+/// Configuration register 1 (01h)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Config1 {
+    /// data rate (DR) and operating mode (MODE)
+    pub data_rate: DataRate,
+    /// conversion mode (CM)
+    pub conversion_mode: ConversionMode,
+    /// voltage reference selection (VREF)
+    pub v_ref: VRefSource,
+    /// temperature sensor mode (TS)
+    pub temperature_sensor_mode: bool,
+}
+
+impl Config1 {
+    /// Register address
+    pub const ADDRESS: u8 = 0x01;
+
+    /// Encode the register value
+    pub fn to_bits(&self) -> u8 {
+        // the data rate already carries the turbo bit, which is the MODE bit below DR
+        ((self.data_rate as u8) << 4)
+            | ((self.conversion_mode as u8) << 3)
+            | ((self.v_ref as u8) << 1)
+            | (self.temperature_sensor_mode as u8)
+    }
+
+    /// Decode the register value, failing on reserved bit patterns
+    pub fn from_bits(val: u8) -> Result<Self, DecodeError> {
+        Ok(Config1 {
+            data_rate: DataRate::try_from(val >> 4)?,
+            conversion_mode: ConversionMode::try_from((val >> 3) & 0b1)?,
+            v_ref: VRefSource::try_from((val >> 1) & 0b11)?,
+            temperature_sensor_mode: (val & 0b1) == 1,
+        })
+    }
+
+    /// Whether turbo mode (MODE) is selected by the data rate
+    pub fn turbo_mode(&self) -> bool {
+        (self.data_rate as u8 & 0b1) == 1
+    }
+}
+
+impl Default for Config1 {
+    fn default() -> Self {
+        Config1 {
+            data_rate: DataRate::Sps20Normal,
+            conversion_mode: ConversionMode::SingleShot,
+            v_ref: VRefSource::Internal,
+            temperature_sensor_mode: false,
+        }
+    }
+}
+
+/// Configuration register 2 (02h)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Config2 {
+    /// conversion result ready flag (DRDY), read-only
+    pub data_ready: bool,
+    /// data counter enable (DCNT)
+    pub data_counter: bool,
+    /// data integrity check (CRC)
+    pub crc: Crc,
+    /// 10 uA burn-out current sources (BCS)
+    pub burn_out_current_sources: bool,
+    /// excitation current level (IDAC)
+    pub current_source: CurrentSource,
+}
+
+impl Config2 {
+    /// Register address
+    pub const ADDRESS: u8 = 0x02;
+
+    /// Encode the register value
+    pub fn to_bits(&self) -> u8 {
+        ((self.data_ready as u8) << 7)
+            | ((self.data_counter as u8) << 6)
+            | ((self.crc as u8) << 4)
+            | ((self.burn_out_current_sources as u8) << 3)
+            | (self.current_source as u8)
+    }
+
+    /// Decode the register value, failing on reserved bit patterns
+    pub fn from_bits(val: u8) -> Result<Self, DecodeError> {
+        Ok(Config2 {
+            data_ready: ((val >> 7) & 0b1) == 1,
+            data_counter: ((val >> 6) & 0b1) == 1,
+            crc: Crc::try_from((val >> 4) & 0b11)?,
+            burn_out_current_sources: ((val >> 3) & 0b1) == 1,
+            current_source: CurrentSource::try_from(val & 0b111)?,
+        })
+    }
+}
+
+impl Default for Config2 {
+    fn default() -> Self {
+        Config2 {
+            data_ready: false,
+            data_counter: false,
+            crc: Crc::Disabled,
+            burn_out_current_sources: false,
+            current_source: CurrentSource::Off,
+        }
+    }
+}
+
+/// Configuration register 3 (03h)
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Config3 {
+    /// routing of excitation current source 1 (I1MUX)
+    pub current_route_1: CurrentRoute,
+    /// routing of excitation current source 2 (I2MUX)
+    pub current_route_2: CurrentRoute,
+    /// automatic data read mode (AUTO), ADS122U04 only
+    pub auto_read: bool,
+}
+
+impl Config3 {
+    /// Register address
+    pub const ADDRESS: u8 = 0x03;
+
+    /// Encode the register value
+    pub fn to_bits(&self) -> u8 {
+        ((self.current_route_1 as u8) << 5)
+            | ((self.current_route_2 as u8) << 2)
+            | (self.auto_read as u8)
+    }
+
+    /// Decode the register value, failing on reserved bit patterns
+    pub fn from_bits(val: u8) -> Result<Self, DecodeError> {
+        Ok(Config3 {
+            current_route_1: CurrentRoute::try_from(val >> 5)?,
+            current_route_2: CurrentRoute::try_from((val >> 2) & 0b111)?,
+            auto_read: (val & 0b1) == 1,
+        })
+    }
+}
+
+impl Default for Config3 {
+    fn default() -> Self {
+        Config3 {
+            current_route_1: CurrentRoute::Off,
+            current_route_2: CurrentRoute::Off,
+            auto_read: false,
+        }
+    }
+}
+
+/// Configuration register 4 (04h), ADS122U04 only
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Default)]
+pub struct Config4 {
+    /// direction of GPIO0..GPIO2 (GPIOxDIR), `true` for output
+    pub gpio_output: [bool; 3],
+    /// GPIO2 acts as DRDY output (GPIO2SEL)
+    pub gpio2_drdy: bool,
+    /// data of GPIO0..GPIO2 (GPIOxDAT)
+    pub gpio_data: [bool; 3],
+}
+
+impl Config4 {
+    /// Register address
+    pub const ADDRESS: u8 = 0x04;
+
+    /// Encode the register value
+    pub fn to_bits(&self) -> u8 {
+        let mut val = (self.gpio2_drdy as u8) << 3;
+        for (i, (output, data)) in self.gpio_output.iter().zip(self.gpio_data).enumerate() {
+            val |= ((*output as u8) << (4 + i)) | ((data as u8) << i);
+        }
+        val
+    }
+
+    /// Decode the register value
+    pub fn from_bits(val: u8) -> Self {
+        Config4 {
+            gpio_output: [0, 1, 2].map(|i| ((val >> (4 + i)) & 0b1) == 1),
+            gpio2_drdy: ((val >> 3) & 0b1) == 1,
+            gpio_data: [0, 1, 2].map(|i| ((val >> i) & 0b1) == 1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Decode every value of a field and check that the valid ones encode back to themselves,
+    /// returning the number of valid values
+    fn round_trip<T>(bits: u32, encode: fn(T) -> u8) -> usize
+    where
+        T: TryFrom<u8, Error = DecodeError>,
+    {
+        let mut valid = 0;
+        for val in 0..(1u8 << bits) {
+            match T::try_from(val) {
+                Ok(field) => {
+                    assert_eq!(encode(field), val);
+                    valid += 1;
+                }
+                Err(e) => assert_eq!(e, DecodeError(val)),
+            }
+        }
+        valid
+    }
+
+    #[test]
+    fn enum_round_trip() {
+        assert_eq!(round_trip::<Mux>(4, |mux| mux as u8), 15);
+        assert_eq!(round_trip::<DataRate>(4, |rate| rate as u8), 14);
+        assert_eq!(round_trip::<Gain>(3, |gain| gain as u8), 8);
+        assert_eq!(round_trip::<CurrentSource>(3, |current| current as u8), 8);
+        assert_eq!(round_trip::<CurrentRoute>(3, |route| route as u8), 7);
+        assert_eq!(round_trip::<ConversionMode>(1, |mode| mode as u8), 2);
+        assert_eq!(round_trip::<Crc>(2, |crc| crc as u8), 3);
+    }
+
+    #[test]
+    fn enum_reserved_patterns() {
+        assert_eq!(Mux::try_from(0b1111), Err(DecodeError(0b1111)));
+        assert_eq!(DataRate::try_from(0b1110), Err(DecodeError(0b1110)));
+        assert_eq!(DataRate::try_from(0b1111), Err(DecodeError(0b1111)));
+        assert_eq!(Crc::try_from(0b11), Err(DecodeError(0b11)));
+        assert_eq!(CurrentRoute::try_from(0b111), Err(DecodeError(0b111)));
+        assert_eq!(ConversionMode::try_from(2), Err(DecodeError(2)));
+    }
+
+    #[test]
+    fn vref_source_round_trip() {
+        for source in [
+            VRefSource::Internal,
+            VRefSource::External,
+            VRefSource::AnalogSupply,
+        ] {
+            assert_eq!(VRefSource::try_from(source as u8), Ok(source));
+        }
+        // both encodings select the analog supply
+        assert_eq!(VRefSource::try_from(0b11), Ok(VRefSource::AnalogSupply));
+    }
+
+    #[test]
+    fn config0_round_trip() {
+        for val in 0..=255u8 {
+            match Config0::from_bits(val) {
+                Ok(config) => assert_eq!(config.to_bits(), val),
+                Err(e) => {
+                    assert_eq!(val >> 4, 0b1111);
+                    assert_eq!(e, DecodeError(0b1111));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn config1_round_trip() {
+        for val in 0..=255u8 {
+            // VREF 0b11 is an alias of 0b10
+            let expected = if (val >> 1) & 0b11 == 0b11 {
+                val & !0b010
+            } else {
+                val
+            };
+            match Config1::from_bits(val) {
+                Ok(config) => assert_eq!(config.to_bits(), expected),
+                Err(e) => {
+                    assert!(val >> 4 >= 0b1110);
+                    assert_eq!(e, DecodeError(val >> 4));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn config2_round_trip() {
+        for val in 0..=255u8 {
+            match Config2::from_bits(val) {
+                Ok(config) => assert_eq!(config.to_bits(), val),
+                Err(e) => {
+                    assert_eq!((val >> 4) & 0b11, 0b11);
+                    assert_eq!(e, DecodeError(0b11));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn config3_round_trip() {
+        for val in 0..=255u8 {
+            match Config3::from_bits(val) {
+                // bit 1 is reserved
+                Ok(config) => assert_eq!(config.to_bits(), val & !0b10),
+                Err(e) => {
+                    assert!(val >> 5 == 0b111 || (val >> 2) & 0b111 == 0b111);
+                    assert_eq!(e, DecodeError(0b111));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn config4_round_trip() {
+        for val in 0..=255u8 {
+            // bit 7 is reserved
+            assert_eq!(Config4::from_bits(val).to_bits(), val & 0x7F);
+        }
+    }
+}
@@ -0,0 +1,287 @@
+//! RTD measurement with Callendar–Van Dusen conversion
+//!
+//! The RTD is excited by the internal current sources (IDAC) and measured ratiometrically:
+//! the excitation current also flows through a reference resistor between REFP and REFN,
+//! which serves as the external voltage reference. The result then only depends on the
+//! ratio of the RTD to the reference resistance and not on the accuracy of the current.
+
+use embedded_hal::digital::InputPin;
+
+use crate::config::Config;
+use crate::interface::{ReadData, WriteData};
+use crate::registers::*;
+use crate::{ADS122x04, Error, Sample, SensorLimits};
+
+/// Callendar–Van Dusen coefficient A of IEC 60751 platinum RTDs
+const A: f32 = 3.9083e-3;
+/// Callendar–Van Dusen coefficient B of IEC 60751 platinum RTDs
+const B: f32 = -5.775e-7;
+/// Callendar–Van Dusen coefficient C of IEC 60751 platinum RTDs, only applies below 0 °C
+const C: f32 = -4.183e-12;
+
+/// Platinum RTD according to IEC 60751
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum RtdSensor {
+    /// 100 Ω at 0 °C
+    Pt100,
+    /// 500 Ω at 0 °C
+    Pt500,
+    /// 1000 Ω at 0 °C
+    Pt1000,
+}
+
+impl RtdSensor {
+    /// Nominal resistance at 0 °C in Ω
+    pub fn r0(&self) -> f32 {
+        match self {
+            RtdSensor::Pt100 => 100.0,
+            RtdSensor::Pt500 => 500.0,
+            RtdSensor::Pt1000 => 1000.0,
+        }
+    }
+
+    /// Resistance in Ω at the given temperature in °C (Callendar–Van Dusen equation)
+    pub fn resistance(&self, celsius: f32) -> f32 {
+        let t = celsius;
+        let c = if t < 0.0 { C } else { 0.0 };
+        self.r0() * (1.0 + A * t + B * t * t + c * (t - 100.0) * t * t * t)
+    }
+
+    /// Temperature in °C at the given resistance in Ω, the inverse of
+    /// [`resistance`](Self::resistance). Valid from -200 °C to 850 °C.
+    pub fn temperature(&self, resistance: f32) -> f32 {
+        let r0 = self.r0();
+        // start from the linear approximation and refine with Newton's method
+        let mut t = (resistance / r0 - 1.0) / A;
+        for _ in 0..8 {
+            let c = if t < 0.0 { C } else { 0.0 };
+            let slope = r0 * (A + 2.0 * B * t + c * (4.0 * t - 300.0) * t * t);
+            let step = (self.resistance(t) - resistance) / slope;
+            t -= step;
+            if step.abs() < 1e-4 {
+                break;
+            }
+        }
+        t
+    }
+}
+
+/// Wiring of the RTD
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Wiring {
+    /// The measured resistance includes both leads. Their combined resistance in Ω is
+    /// subtracted from the result.
+    TwoWire {
+        /// resistance of both leads together in Ω
+        lead_resistance: f32,
+    },
+    /// A second matched current source on the compensating lead cancels the lead resistance.
+    /// Both currents flow through the reference resistor.
+    ThreeWire,
+    /// Separate sense leads, the lead resistance does not affect the result
+    FourWire,
+}
+
+/// Result of an RTD measurement
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RtdMeasurement {
+    /// resistance of the RTD in Ω
+    pub resistance: f32,
+    /// temperature in °C
+    pub temperature: f32,
+}
+
+/// RTD circuit connected to the ADC
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Rtd {
+    /// type of the RTD
+    pub sensor: RtdSensor,
+    /// wiring of the RTD
+    pub wiring: Wiring,
+    /// resistance of the reference resistor between REFP and REFN in Ω
+    pub reference_resistance: f32,
+    /// inputs across the RTD (MUX)
+    pub mux: Mux,
+    /// gain of the PGA (GAIN)
+    pub gain: Gain,
+    /// excitation current level (IDAC)
+    pub current: CurrentSource,
+    /// routing of the excitation current into the RTD (I1MUX)
+    pub current_route_1: CurrentRoute,
+    /// routing of the compensating excitation current of a 3-wire RTD (I2MUX), unused otherwise
+    pub current_route_2: CurrentRoute,
+}
+
+impl Rtd {
+    /// Number of excitation currents flowing through the reference resistor
+    fn reference_currents(&self) -> f32 {
+        match self.wiring {
+            Wiring::ThreeWire => 2.0,
+            Wiring::TwoWire { .. } | Wiring::FourWire => 1.0,
+        }
+    }
+
+    /// Configuration measuring the RTD, keeping all other settings of `config`
+    pub fn config(&self, config: &Config) -> Config {
+        let reference_voltage =
+            self.current.to_amps() * self.reference_resistance * self.reference_currents();
+        Config {
+            v_ref: VRef::External(reference_voltage),
+            gain: self.gain,
+            mux: self.mux,
+            pga_bypass: false,
+            current_source: self.current,
+            current_route_1: self.current_route_1,
+            current_route_2: match self.wiring {
+                Wiring::ThreeWire => self.current_route_2,
+                Wiring::TwoWire { .. } | Wiring::FourWire => CurrentRoute::Off,
+            },
+            temperature_sensor_mode: false,
+            ..*config
+        }
+    }
+
+    /// Configure the device for measuring the RTD
+    pub fn configure<BUS, DRDY, E>(&self, adc: &mut ADS122x04<BUS, DRDY>) -> Result<(), Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let config = self.config(adc.config());
+        adc.apply_config(&config)
+    }
+
+    /// RTD resistance in Ω of a sample taken with the configuration of [`config`](Self::config)
+    pub fn resistance(&self, sample: &Sample) -> f32 {
+        let ratio = sample.raw as f32 / (1 << 23) as f32 / sample.gain.to_factor();
+        let resistance = ratio * self.reference_resistance * self.reference_currents();
+        match self.wiring {
+            Wiring::TwoWire { lead_resistance } => resistance - lead_resistance,
+            Wiring::ThreeWire | Wiring::FourWire => resistance,
+        }
+    }
+
+    /// Configure the device, take a measurement and convert it to resistance and
+    /// temperature. A saturated result is reported as [`Error::OpenInput`] and a result that
+    /// is not positive as [`Error::ShortedInput`].
+    pub fn measure<BUS, DRDY, E>(
+        &self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+    ) -> Result<RtdMeasurement, Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let config = self.config(adc.config());
+        let sample = adc.measure_sensor(&config, SensorLimits::OpenHigh)?;
+        let resistance = self.resistance(&sample);
+        Ok(RtdMeasurement {
+            resistance,
+            temperature: self.sensor.temperature(resistance),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Continuity;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} differs from {expected} by more than {tolerance}"
+        );
+    }
+
+    fn sample(raw: i32, gain: Gain) -> Sample {
+        Sample {
+            raw,
+            counter: None,
+            continuity: Continuity::Unknown,
+            gain,
+            v_ref: VRef::External(0.81),
+            gain_correction: 1.0,
+        }
+    }
+
+    fn rtd(wiring: Wiring) -> Rtd {
+        Rtd {
+            sensor: RtdSensor::Pt100,
+            wiring,
+            reference_resistance: 1620.0,
+            mux: Mux::Ain1Ain0,
+            gain: Gain::Gain4,
+            current: CurrentSource::I500uA,
+            current_route_1: CurrentRoute::Ain2,
+            current_route_2: CurrentRoute::Ain3,
+        }
+    }
+
+    /// IEC 60751 table values of a Pt100 as (°C, Ω)
+    const PT100: [(f32, f32); 6] = [
+        (-200.0, 18.520),
+        (-100.0, 60.256),
+        (0.0, 100.0),
+        (100.0, 138.506),
+        (500.0, 280.978),
+        (850.0, 390.481),
+    ];
+
+    #[test]
+    fn callendar_van_dusen() {
+        for (celsius, resistance) in PT100 {
+            assert_close(RtdSensor::Pt100.resistance(celsius), resistance, 1e-3);
+            assert_close(RtdSensor::Pt500.resistance(celsius), 5.0 * resistance, 5e-3);
+            assert_close(
+                RtdSensor::Pt1000.resistance(celsius),
+                10.0 * resistance,
+                1e-2,
+            );
+        }
+    }
+
+    #[test]
+    fn inverse() {
+        for (celsius, resistance) in PT100 {
+            assert_close(RtdSensor::Pt100.temperature(resistance), celsius, 5e-3);
+            assert_close(
+                RtdSensor::Pt1000.temperature(10.0 * resistance),
+                celsius,
+                5e-3,
+            );
+        }
+        for sensor in [RtdSensor::Pt100, RtdSensor::Pt500, RtdSensor::Pt1000] {
+            for celsius in (-200..=850).step_by(25).map(|t| t as f32) {
+                assert_close(
+                    sensor.temperature(sensor.resistance(celsius)),
+                    celsius,
+                    5e-3,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn ratiometric_wiring() {
+        // 1/16 of the reference resistance at gain 4
+        let sample = sample(1 << 21, Gain::Gain4);
+        assert_close(rtd(Wiring::FourWire).resistance(&sample), 101.25, 1e-4);
+        // both excitation currents flow through the reference resistor
+        assert_close(rtd(Wiring::ThreeWire).resistance(&sample), 202.5, 1e-4);
+        let two_wire = rtd(Wiring::TwoWire {
+            lead_resistance: 1.25,
+        });
+        assert_close(two_wire.resistance(&sample), 100.0, 1e-4);
+    }
+
+    #[test]
+    fn reference_voltage() {
+        let config = rtd(Wiring::ThreeWire).config(&Config::default());
+        assert_close(config.v_ref.to_voltage(), 1.62, 1e-6);
+        assert_eq!(config.current_route_2, CurrentRoute::Ain3);
+        let config = rtd(Wiring::FourWire).config(&Config::default());
+        assert_close(config.v_ref.to_voltage(), 0.81, 1e-6);
+        assert_eq!(config.current_route_2, CurrentRoute::Off);
+    }
+}
@@ -0,0 +1,119 @@
+//! Multi-channel scan sequencer
+
+use embedded_hal::digital::InputPin;
+
+use crate::config::Config;
+use crate::interface::{ReadData, WriteData};
+use crate::registers::*;
+use crate::{ADS122x04, Continuity, Error, Sample};
+
+/// Settings of a single channel of a [`Scanner`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Channel {
+    /// input multiplexer (MUX)
+    pub mux: Mux,
+    /// gain of the PGA (GAIN)
+    pub gain: Gain,
+    /// bypass the PGA (PGA_BYPASS)
+    pub pga_bypass: bool,
+    /// excitation current level (IDAC)
+    pub current_source: CurrentSource,
+    /// routing of excitation current source 1 (I1MUX)
+    pub current_route_1: CurrentRoute,
+    /// routing of excitation current source 2 (I2MUX)
+    pub current_route_2: CurrentRoute,
+    /// data rate (DR) and operating mode (MODE)
+    pub data_rate: DataRate,
+    /// number of conversions discarded after switching to the channel, e.g. to let external
+    /// filters or excitation currents settle
+    pub discard: u8,
+}
+
+impl Channel {
+    /// Channel on the given input with gain 1, the PGA enabled, excitation currents off,
+    /// 20 SPS and no discarded conversions
+    pub const fn new(mux: Mux) -> Self {
+        Channel {
+            mux,
+            gain: Gain::Gain1,
+            pga_bypass: false,
+            current_source: CurrentSource::Off,
+            current_route_1: CurrentRoute::Off,
+            current_route_2: CurrentRoute::Off,
+            data_rate: DataRate::Sps20Normal,
+            discard: 0,
+        }
+    }
+
+    /// Configuration selecting the channel, keeping all other settings of `config`
+    fn apply(&self, config: &Config) -> Config {
+        Config {
+            mux: self.mux,
+            gain: self.gain,
+            pga_bypass: self.pga_bypass,
+            current_source: self.current_source,
+            current_route_1: self.current_route_1,
+            current_route_2: self.current_route_2,
+            data_rate: self.data_rate,
+            conversion_mode: ConversionMode::SingleShot,
+            temperature_sensor_mode: false,
+            ..*config
+        }
+    }
+}
+
+/// Cycles through a fixed list of channels, taking one single-shot measurement each.
+///
+/// Single-shot conversions are fully settled, so switching channels needs no discarded
+/// conversions unless [`Channel::discard`] asks for them. Only the config registers that
+/// differ between consecutive channels are rewritten. The device is left configured for the
+/// last channel.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Scanner<const N: usize> {
+    channels: [Channel; N],
+}
+
+impl<const N: usize> Scanner<N> {
+    /// Create a scanner for the given channels, measured in order
+    pub const fn new(channels: [Channel; N]) -> Self {
+        Scanner { channels }
+    }
+
+    /// The channels of the scanner
+    pub fn channels(&self) -> &[Channel; N] {
+        &self.channels
+    }
+
+    /// Measure all channels once, returning the samples in channel order
+    pub fn scan<BUS, DRDY, E>(
+        &self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+    ) -> Result<[Sample; N], Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let mut samples = [Sample {
+            raw: 0,
+            counter: None,
+            continuity: Continuity::Unknown,
+            gain: Gain::Gain1,
+            v_ref: VRef::Internal,
+            gain_correction: 1.0,
+        }; N];
+        adc.pending_measurement = None;
+        for (channel, sample) in self.channels.iter().zip(samples.iter_mut()) {
+            let config = channel.apply(adc.config());
+            adc.apply_config(&config)?;
+            for _ in 0..channel.discard {
+                adc.start()?;
+                adc.wait_for_data()?;
+                adc.read_sample()?;
+            }
+            adc.start()?;
+            adc.wait_for_data()?;
+            *sample = adc.read_sample()?;
+        }
+        Ok(samples)
+    }
+}
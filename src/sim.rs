@@ -0,0 +1,319 @@
+//! In-memory ADS122x04 simulator, available with the `sim` feature
+//!
+//! [`Simulator`] implements the I2C (ADS122C04) and UART (ADS122U04) bus traits so that
+//! [`ADS122x04`](crate::ADS122x04) can be driven without hardware. Conversions complete
+//! instantly on START/SYNC and yield the programmed result. Pass `&mut Simulator` to the
+//! driver to inspect the simulated device afterwards.
+
+use embedded_hal::i2c;
+use embedded_io::{ErrorType, Read, Write};
+
+use crate::interface::crc16;
+use crate::registers::*;
+
+/// Error of the simulated bus
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum SimError {
+    /// The I2C address does not match the simulated device
+    NoAcknowledge,
+    /// An unknown command or register address has been sent
+    InvalidCommand(u8),
+    /// A UART command was not preceded by the 0x55 sync byte
+    MissingSync(u8),
+}
+
+impl i2c::Error for SimError {
+    fn kind(&self) -> i2c::ErrorKind {
+        match self {
+            SimError::NoAcknowledge => {
+                i2c::ErrorKind::NoAcknowledge(i2c::NoAcknowledgeSource::Address)
+            }
+            _ => i2c::ErrorKind::Other,
+        }
+    }
+}
+
+impl embedded_io::Error for SimError {
+    fn kind(&self) -> embedded_io::ErrorKind {
+        embedded_io::ErrorKind::InvalidData
+    }
+}
+
+/// Progress of a UART command
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum UartState {
+    /// waiting for the sync byte
+    Sync,
+    /// waiting for the command byte
+    Command,
+    /// waiting for the data byte of a WREG command
+    Data(u8),
+}
+
+/// Simulated ADS122C04 (I2C) or ADS122U04 (UART)
+#[derive(Debug, Clone)]
+pub struct Simulator {
+    address: u8,
+    registers: [u8; 5],
+    data: u32,
+    counter: u8,
+    powered_down: bool,
+    result: i32,
+    conversion: Option<fn(&Simulator) -> i32>,
+    conversions: u32,
+    gpio_levels: u8,
+    response: [u8; 8],
+    response_len: usize,
+    response_pos: usize,
+    uart_state: UartState,
+}
+
+impl Simulator {
+    fn new(address: u8) -> Self {
+        Simulator {
+            address,
+            registers: [0; 5],
+            data: 0,
+            counter: 0,
+            powered_down: false,
+            result: 0,
+            conversion: None,
+            conversions: 0,
+            gpio_levels: 0,
+            response: [0; 8],
+            response_len: 0,
+            response_pos: 0,
+            uart_state: UartState::Sync,
+        }
+    }
+
+    /// Simulate an ADS122C04 listening on the given I2C address
+    pub fn new_i2c(address: u8) -> Self {
+        Self::new(address)
+    }
+
+    /// Simulate an ADS122U04 on a UART
+    pub fn new_serial() -> Self {
+        Self::new(0)
+    }
+
+    /// Value of a config register
+    pub fn register(&self, address: u8) -> u8 {
+        self.registers[address as usize]
+    }
+
+    /// Overwrite a config register, e.g. to simulate a brown-out or a corrupted register
+    pub fn set_register(&mut self, address: u8, val: u8) {
+        self.registers[address as usize] = val;
+    }
+
+    /// Levels applied to GPIO0..GPIO2 of the ADS122U04 from outside, bit n for GPIOn. Pins
+    /// configured as inputs read these levels in config register 4.
+    pub fn set_gpio_levels(&mut self, levels: u8) {
+        self.gpio_levels = levels & 0b111;
+    }
+
+    /// Signed 24-bit result of all following conversions
+    pub fn set_result(&mut self, raw: i32) {
+        self.result = raw;
+        self.conversion = None;
+    }
+
+    /// Compute the result of each following conversion from the simulated device, e.g.
+    /// depending on the selected input with [`Simulator::mux`]
+    pub fn set_conversion(&mut self, conversion: fn(&Simulator) -> i32) {
+        self.conversion = Some(conversion);
+    }
+
+    /// Input multiplexer selected in config register 0
+    pub fn mux(&self) -> Result<Mux, DecodeError> {
+        Mux::try_from(self.registers[0] >> 4)
+    }
+
+    /// Whether the device is in power-down mode
+    pub fn is_powered_down(&self) -> bool {
+        self.powered_down
+    }
+
+    /// Number of conversions performed since the simulator was created
+    pub fn conversions(&self) -> u32 {
+        self.conversions
+    }
+
+    /// Complete a conversion and latch its result
+    fn convert(&mut self) {
+        let raw = match self.conversion {
+            Some(conversion) => conversion(self),
+            None => self.result,
+        };
+        self.data = (raw as u32) & 0x00FF_FFFF;
+        self.counter = self.counter.wrapping_add(1);
+        self.conversions += 1;
+        self.registers[2] |= 0x80;
+    }
+
+    /// Queue a response followed by the integrity bytes selected in config register 2
+    fn respond(&mut self, data: &[u8]) {
+        let len = data.len();
+        self.response[..len].copy_from_slice(data);
+        self.response_len = len;
+        match (self.registers[2] >> 4) & 0b11 {
+            0b01 => {
+                for (inverted, byte) in self.response[len..].iter_mut().zip(data) {
+                    *inverted = !byte;
+                }
+                self.response_len += len;
+            }
+            0b10 => {
+                let crc = crc16(data);
+                self.response[len] = (crc >> 8) as u8;
+                self.response[len + 1] = crc as u8;
+                self.response_len += 2;
+            }
+            _ => {}
+        }
+        self.response_pos = 0;
+    }
+
+    /// Queue the conversion data, preceded by the conversion counter if it is enabled
+    fn respond_data(&mut self) {
+        let [_, msb, csb, lsb] = self.data.to_be_bytes();
+        if (self.registers[2] >> 6) & 0b1 == 1 {
+            self.respond(&[self.counter, msb, csb, lsb]);
+        } else {
+            self.respond(&[msb, csb, lsb]);
+        }
+        self.registers[2] &= 0x7F;
+        // in continuous conversion mode the next conversion is already waiting
+        if (self.registers[1] >> 3) & 0b1 == 1 && !self.powered_down {
+            self.convert();
+        }
+    }
+
+    /// Execute a command, `register` is the register address encoded in RREG/WREG
+    fn execute(&mut self, command: u8, register: u8, data: Option<u8>) -> Result<(), SimError> {
+        match command {
+            0x06 | 0x07 => {
+                *self = Simulator {
+                    result: self.result,
+                    conversion: self.conversion,
+                    conversions: self.conversions,
+                    gpio_levels: self.gpio_levels,
+                    ..Self::new(self.address)
+                };
+            }
+            0x08 | 0x09 => {
+                self.powered_down = false;
+                self.convert();
+            }
+            0x02 | 0x03 => self.powered_down = true,
+            0x10..=0x1F => self.respond_data(),
+            0x20..=0x2F if register == 4 => {
+                // the data bits of input pins reflect the applied levels
+                let inputs = !(self.registers[4] >> 4) & 0b111;
+                self.respond(&[(self.registers[4] & !inputs) | (self.gpio_levels & inputs)]);
+            }
+            0x20..=0x2F => self.respond(&[self.registers[register as usize]]),
+            0x40..=0x4F => {
+                let val = data.unwrap_or_default();
+                if register == 2 {
+                    // DRDY is read-only
+                    self.registers[2] = (self.registers[2] & 0x80) | (val & 0x7F);
+                } else {
+                    self.registers[register as usize] = val;
+                }
+            }
+            _ => return Err(SimError::InvalidCommand(command)),
+        }
+        Ok(())
+    }
+
+    /// Copy the queued response into `buffer`, padding with zeros once it is exhausted
+    fn read_response(&mut self, buffer: &mut [u8]) {
+        for byte in buffer.iter_mut() {
+            *byte = if self.response_pos < self.response_len {
+                self.response_pos += 1;
+                self.response[self.response_pos - 1]
+            } else {
+                0
+            };
+        }
+    }
+}
+
+impl i2c::ErrorType for Simulator {
+    type Error = SimError;
+}
+
+impl i2c::I2c for Simulator {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        if address != self.address {
+            return Err(SimError::NoAcknowledge);
+        }
+        for operation in operations {
+            match operation {
+                i2c::Operation::Write(bytes) => {
+                    if let Some(&command) = bytes.first() {
+                        // rrxx on the ADS122C04
+                        let register = (command >> 2) & 0b11;
+                        self.execute(command, register, bytes.get(1).copied())?;
+                    }
+                }
+                i2c::Operation::Read(buffer) => self.read_response(buffer),
+            }
+        }
+        Ok(())
+    }
+}
+
+impl ErrorType for Simulator {
+    type Error = SimError;
+}
+
+impl Write for Simulator {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        for &byte in buf {
+            self.uart_state = match self.uart_state {
+                UartState::Sync if byte == 0x55 => UartState::Command,
+                UartState::Sync => return Err(SimError::MissingSync(byte)),
+                UartState::Command if byte & 0xF0 == 0x40 => UartState::Data(byte),
+                UartState::Command | UartState::Data(_) => {
+                    let (command, data) = match self.uart_state {
+                        UartState::Data(command) => (command, Some(byte)),
+                        _ => (byte, None),
+                    };
+                    // rrrx on the ADS122U04
+                    let register = (command >> 1) & 0b111;
+                    if command & 0xE0 != 0 && register > 4 {
+                        return Err(SimError::InvalidCommand(command));
+                    }
+                    self.execute(command, register, data)?;
+                    UartState::Sync
+                }
+            };
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl Read for Simulator {
+    /// Returns `Ok(0)` when no response is pending, like a UART that timed out. In automatic
+    /// data read mode (AUTO) the pending conversion data is sent without a command.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let drdy = (self.registers[2] >> 7) & 0b1 == 1;
+        if self.response_pos == self.response_len && self.registers[3] & 0b1 == 1 && drdy {
+            self.respond_data();
+        }
+        let len = buf.len().min(self.response_len - self.response_pos);
+        self.read_response(&mut buf[..len]);
+        Ok(len)
+    }
+}
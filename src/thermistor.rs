@@ -0,0 +1,341 @@
+//! NTC thermistor measurement with Beta and Steinhart–Hart models
+//!
+//! The thermistor is either part of a voltage divider supplied by AVDD, which is measured
+//! ratiometrically against the analog supply, or excited by an internal current source (IDAC)
+//! and measured against the internal reference.
+
+use embedded_hal::digital::InputPin;
+
+use crate::config::Config;
+use crate::interface::{ReadData, WriteData};
+use crate::registers::*;
+use crate::{ADS122x04, Error, Sample, SensorLimits};
+
+/// 0 °C in K
+const ZERO_CELSIUS: f64 = 273.15;
+
+/// Resistance to temperature relation of a thermistor
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ThermistorModel {
+    /// Beta model, 1/T = 1/T0 + ln(R/R0) / B
+    Beta {
+        /// resistance in Ω at the reference temperature
+        r0: f32,
+        /// reference temperature in °C, usually 25 °C
+        t0: f32,
+        /// B constant in K
+        beta: f32,
+    },
+    /// Steinhart–Hart equation, 1/T = A + B ln(R) + C ln(R)^3 with T in K
+    SteinhartHart {
+        /// coefficient A
+        a: f64,
+        /// coefficient B
+        b: f64,
+        /// coefficient C
+        c: f64,
+    },
+}
+
+impl ThermistorModel {
+    /// Solve the Steinhart–Hart coefficients from three calibration points given as
+    /// (resistance in Ω, temperature in °C). Returns `None` if the points do not determine the
+    /// coefficients, e.g. because two of them coincide.
+    pub fn steinhart_hart_from_points(points: [(f32, f32); 3]) -> Option<Self> {
+        let [l1, l2, l3] = points.map(|(resistance, _)| libm::log(resistance as f64));
+        let [y1, y2, y3] = points.map(|(_, celsius)| 1.0 / (celsius as f64 + ZERO_CELSIUS));
+        let gamma2 = (y2 - y1) / (l2 - l1);
+        let gamma3 = (y3 - y1) / (l3 - l1);
+        let c = (gamma3 - gamma2) / (l3 - l2) / (l1 + l2 + l3);
+        let b = gamma2 - c * (l1 * l1 + l1 * l2 + l2 * l2);
+        let a = y1 - (b + l1 * l1 * c) * l1;
+        if a.is_finite() && b.is_finite() && c.is_finite() {
+            Some(ThermistorModel::SteinhartHart { a, b, c })
+        } else {
+            None
+        }
+    }
+
+    /// Temperature in °C at the given resistance in Ω
+    pub fn temperature(&self, resistance: f32) -> f32 {
+        let ln_r = libm::log(resistance as f64);
+        let inverse = match *self {
+            ThermistorModel::Beta { r0, t0, beta } => {
+                1.0 / (t0 as f64 + ZERO_CELSIUS) + (ln_r - libm::log(r0 as f64)) / beta as f64
+            }
+            ThermistorModel::SteinhartHart { a, b, c } => a + b * ln_r + c * ln_r * ln_r * ln_r,
+        };
+        (1.0 / inverse - ZERO_CELSIUS) as f32
+    }
+
+    /// Resistance in Ω at the given temperature in °C
+    pub fn resistance(&self, celsius: f32) -> f32 {
+        let inverse = 1.0 / (celsius as f64 + ZERO_CELSIUS);
+        let ln_r = match *self {
+            ThermistorModel::Beta { r0, t0, beta } => {
+                libm::log(r0 as f64) + beta as f64 * (inverse - 1.0 / (t0 as f64 + ZERO_CELSIUS))
+            }
+            ThermistorModel::SteinhartHart { a, b, c } => {
+                // start from the solution without the cubic term and refine with Newton's method
+                let mut x = (inverse - a) / b;
+                for _ in 0..8 {
+                    let step = (a + b * x + c * x * x * x - inverse) / (b + 3.0 * c * x * x);
+                    x -= step;
+                    if libm::fabs(step) < 1e-9 {
+                        break;
+                    }
+                }
+                x
+            }
+        };
+        libm::exp(ln_r) as f32
+    }
+}
+
+/// Position of the thermistor in a voltage divider
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DividerPosition {
+    /// The thermistor connects the measured node to AVSS, the series resistor to AVDD
+    Low,
+    /// The thermistor connects the measured node to AVDD, the series resistor to AVSS
+    High,
+}
+
+/// Excitation of the thermistor
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Excitation {
+    /// Voltage divider supplied by AVDD, measured ratiometrically against the analog supply
+    Divider {
+        /// resistance of the series resistor in Ω
+        series_resistance: f32,
+        /// position of the thermistor in the divider
+        position: DividerPosition,
+        /// supply voltage (AVDD - AVSS) in V, only used to convert results to voltages
+        supply: f32,
+    },
+    /// Internal current source (IDAC) through the thermistor, measured against the internal
+    /// reference. The result is subject to the accuracy of the current source.
+    Current {
+        /// excitation current level (IDAC)
+        current: CurrentSource,
+        /// routing of the excitation current into the thermistor (I1MUX)
+        route: CurrentRoute,
+    },
+}
+
+/// Result of a thermistor measurement
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ThermistorMeasurement {
+    /// resistance of the thermistor in Ω
+    pub resistance: f32,
+    /// temperature in °C
+    pub temperature: f32,
+}
+
+/// NTC thermistor circuit connected to the ADC
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Thermistor {
+    /// resistance to temperature relation
+    pub model: ThermistorModel,
+    /// excitation of the thermistor
+    pub excitation: Excitation,
+    /// inputs across the thermistor (MUX), e.g. [`Mux::Ain0Avss`] for a divider
+    pub mux: Mux,
+    /// gain of the PGA (GAIN)
+    pub gain: Gain,
+    /// bypass the PGA (PGA_BYPASS), required for single-ended measurements close to AVSS
+    pub pga_bypass: bool,
+}
+
+impl Thermistor {
+    /// Configuration measuring the thermistor, keeping all other settings of `config`
+    pub fn config(&self, config: &Config) -> Config {
+        let config = Config {
+            gain: self.gain,
+            mux: self.mux,
+            pga_bypass: self.pga_bypass,
+            temperature_sensor_mode: false,
+            ..*config
+        };
+        match self.excitation {
+            Excitation::Divider { supply, .. } => Config {
+                v_ref: VRef::AnalogSupply(supply),
+                current_source: CurrentSource::Off,
+                current_route_1: CurrentRoute::Off,
+                current_route_2: CurrentRoute::Off,
+                ..config
+            },
+            Excitation::Current { current, route } => Config {
+                v_ref: VRef::Internal,
+                current_source: current,
+                current_route_1: route,
+                current_route_2: CurrentRoute::Off,
+                ..config
+            },
+        }
+    }
+
+    /// Configure the device for measuring the thermistor
+    pub fn configure<BUS, DRDY, E>(&self, adc: &mut ADS122x04<BUS, DRDY>) -> Result<(), Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let config = self.config(adc.config());
+        adc.apply_config(&config)
+    }
+
+    /// Thermistor resistance in Ω of a sample taken with the configuration of
+    /// [`config`](Self::config)
+    pub fn resistance(&self, sample: &Sample) -> f32 {
+        match self.excitation {
+            Excitation::Divider {
+                series_resistance,
+                position,
+                ..
+            } => {
+                let ratio = sample.raw as f32 / (1 << 23) as f32 / sample.gain.to_factor();
+                match position {
+                    DividerPosition::Low => series_resistance * ratio / (1.0 - ratio),
+                    DividerPosition::High => series_resistance * (1.0 - ratio) / ratio,
+                }
+            }
+            Excitation::Current { current, .. } => sample.to_voltage() / current.to_amps(),
+        }
+    }
+
+    /// Configure the device, take a measurement and convert it to resistance and temperature.
+    /// A thermistor that is open or shorted is reported as [`Error::OpenInput`] or
+    /// [`Error::ShortedInput`].
+    pub fn measure<BUS, DRDY, E>(
+        &self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+    ) -> Result<ThermistorMeasurement, Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let limits = match self.excitation {
+            Excitation::Divider {
+                position: DividerPosition::High,
+                ..
+            } => SensorLimits::OpenLow,
+            _ => SensorLimits::OpenHigh,
+        };
+        let config = self.config(adc.config());
+        let sample = adc.measure_sensor(&config, limits)?;
+        let resistance = self.resistance(&sample);
+        Ok(ThermistorMeasurement {
+            resistance,
+            temperature: self.model.temperature(resistance),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Continuity;
+
+    fn assert_close(actual: f32, expected: f32, tolerance: f32) {
+        assert!(
+            (actual - expected).abs() <= tolerance,
+            "{actual} differs from {expected} by more than {tolerance}"
+        );
+    }
+
+    fn sample(raw: i32, v_ref: VRef) -> Sample {
+        Sample {
+            raw,
+            counter: None,
+            continuity: Continuity::Unknown,
+            gain: Gain::Gain1,
+            v_ref,
+            gain_correction: 1.0,
+        }
+    }
+
+    fn thermistor(excitation: Excitation) -> Thermistor {
+        Thermistor {
+            model: BETA,
+            excitation,
+            mux: Mux::Ain0Avss,
+            gain: Gain::Gain1,
+            pga_bypass: true,
+        }
+    }
+
+    fn divider(position: DividerPosition) -> Excitation {
+        Excitation::Divider {
+            series_resistance: 10_000.0,
+            position,
+            supply: 3.3,
+        }
+    }
+
+    const BETA: ThermistorModel = ThermistorModel::Beta {
+        r0: 10_000.0,
+        t0: 25.0,
+        beta: 3950.0,
+    };
+
+    /// Calibration points of a 10 kΩ NTC as (Ω, °C)
+    const POINTS: [(f32, f32); 3] = [(32_650.0, 0.0), (10_000.0, 25.0), (3_603.0, 50.0)];
+
+    #[test]
+    fn beta_model() {
+        assert_close(BETA.temperature(10_000.0), 25.0, 1e-4);
+        assert_close(BETA.resistance(25.0), 10_000.0, 1e-2);
+        // 10 kΩ · exp(3950 K · (1 / 323.15 K - 1 / 298.15 K))
+        assert_close(BETA.resistance(50.0), 3588.18, 1e-2);
+        assert_close(BETA.temperature(3588.18), 50.0, 1e-4);
+    }
+
+    #[test]
+    fn steinhart_hart_from_points() {
+        let model = ThermistorModel::steinhart_hart_from_points(POINTS).unwrap();
+        for (resistance, celsius) in POINTS {
+            assert_close(model.temperature(resistance), celsius, 1e-3);
+            assert_close(model.resistance(celsius), resistance, resistance * 1e-5);
+        }
+        for celsius in (-20..=100).step_by(10).map(|t| t as f32) {
+            assert_close(model.temperature(model.resistance(celsius)), celsius, 1e-3);
+        }
+    }
+
+    #[test]
+    fn degenerate_points() {
+        let [p1, p2, p3] = POINTS;
+        for points in [[p1, p1, p3], [p1, p2, p2], [p1, p2, p1]] {
+            assert_eq!(ThermistorModel::steinhart_hart_from_points(points), None);
+        }
+    }
+
+    #[test]
+    fn divider_resistance() {
+        let low = thermistor(divider(DividerPosition::Low));
+        let high = thermistor(divider(DividerPosition::High));
+        // a quarter of the supply across the measured node
+        let quarter = sample(1 << 21, VRef::AnalogSupply(3.3));
+        assert_close(low.resistance(&quarter), 10_000.0 / 3.0, 1e-2);
+        assert_close(high.resistance(&quarter), 30_000.0, 1e-2);
+        // both orientations agree at half the supply
+        let half = sample(1 << 22, VRef::AnalogSupply(3.3));
+        assert_close(low.resistance(&half), 10_000.0, 1e-2);
+        assert_close(high.resistance(&half), 10_000.0, 1e-2);
+    }
+
+    #[test]
+    fn current_resistance() {
+        let thermistor = thermistor(Excitation::Current {
+            current: CurrentSource::I100uA,
+            route: CurrentRoute::Ain0,
+        });
+        let config = thermistor.config(&Config::default());
+        assert_eq!(config.v_ref, VRef::Internal);
+        assert_eq!(config.current_source, CurrentSource::I100uA);
+        // 1.024 V at 100 µA
+        let sample = sample(1 << 22, config.v_ref);
+        assert_close(thermistor.resistance(&sample), 10_240.0, 1e-2);
+    }
+}
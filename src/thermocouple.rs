@@ -0,0 +1,897 @@
+//! Thermocouple measurement with cold-junction compensation
+//!
+//! The thermocouple voltage is measured differentially at high gain against the internal
+//! reference. The internal temperature sensor of the device serves as the cold junction, its
+//! temperature is converted to the equivalent thermocouple voltage and added to the measured
+//! one before converting back to the hot-junction temperature. Both directions use the NIST
+//! ITS-90 reference functions and inverse polynomials (NIST Monograph 175).
+//!
+//! The inputs of a floating thermocouple must be biased within the common-mode range of the
+//! PGA, e.g. with a resistor from the negative input to mid-supply.
+
+use embedded_hal::digital::InputPin;
+
+use crate::config::Config;
+use crate::interface::{ReadData, WriteData};
+use crate::registers::*;
+use crate::{ADS122x04, Error, SensorLimits};
+
+/// Polynomial valid up to the given temperature in °C or thermoelectric voltage in mV
+type Segment = (f64, &'static [f64]);
+
+const K_EMF: [Segment; 2] = [
+    (
+        0.0,
+        &[
+            0.0,
+            0.394501280250e-1,
+            0.236223735980e-4,
+            -0.328589067840e-6,
+            -0.499048287770e-8,
+            -0.675090591730e-10,
+            -0.574103274280e-12,
+            -0.310888728940e-14,
+            -0.104516093650e-16,
+            -0.198892668780e-19,
+            -0.163226974860e-22,
+        ],
+    ),
+    (
+        1372.0,
+        &[
+            -0.176004136860e-1,
+            0.389212049750e-1,
+            0.185587700320e-4,
+            -0.994575928740e-7,
+            0.318409457190e-9,
+            -0.560728448890e-12,
+            0.560750590590e-15,
+            -0.320207200030e-18,
+            0.971511471520e-22,
+            -0.121047212750e-25,
+        ],
+    ),
+];
+
+/// Exponential term a0 * exp(a1 * (t - a2)^2) of the type K reference function above 0 °C
+const K_EMF_EXPONENTIAL: [f64; 3] = [0.118597600000, -0.118343200000e-3, 0.126968600000e3];
+
+const K_TEMPERATURE: [Segment; 3] = [
+    (
+        0.0,
+        &[
+            0.0,
+            2.5173462e1,
+            -1.1662878,
+            -1.0833638,
+            -8.9773540e-1,
+            -3.7342377e-1,
+            -8.6632643e-2,
+            -1.0450598e-2,
+            -5.1920577e-4,
+        ],
+    ),
+    (
+        20.644,
+        &[
+            0.0,
+            2.508355e1,
+            7.860106e-2,
+            -2.503131e-1,
+            8.315270e-2,
+            -1.228034e-2,
+            9.804036e-4,
+            -4.413030e-5,
+            1.057734e-6,
+            -1.052755e-8,
+        ],
+    ),
+    (
+        54.886,
+        &[
+            -1.318058e2,
+            4.830222e1,
+            -1.646031,
+            5.464731e-2,
+            -9.650715e-4,
+            8.802193e-6,
+            -3.110810e-8,
+        ],
+    ),
+];
+
+const J_EMF: [Segment; 2] = [
+    (
+        760.0,
+        &[
+            0.0,
+            0.503811878150e-1,
+            0.304758369300e-4,
+            -0.856810657200e-7,
+            0.132281952950e-9,
+            -0.170529583370e-12,
+            0.209480906970e-15,
+            -0.125383953360e-18,
+            0.156317256970e-22,
+        ],
+    ),
+    (
+        1200.0,
+        &[
+            0.296456256810e3,
+            -0.149761277860e1,
+            0.317871039240e-2,
+            -0.318476867010e-5,
+            0.157208190040e-8,
+            -0.306913690560e-12,
+        ],
+    ),
+];
+
+const J_TEMPERATURE: [Segment; 3] = [
+    (
+        0.0,
+        &[
+            0.0,
+            1.9528268e1,
+            -1.2286185,
+            -1.0752178,
+            -5.9086933e-1,
+            -1.7256713e-1,
+            -2.8131513e-2,
+            -2.3963370e-3,
+            -8.3823321e-5,
+        ],
+    ),
+    (
+        42.919,
+        &[
+            0.0,
+            1.978425e1,
+            -2.001204e-1,
+            1.036969e-2,
+            -2.549687e-4,
+            3.585153e-6,
+            -5.344285e-8,
+            5.099890e-10,
+        ],
+    ),
+    (
+        69.553,
+        &[
+            -3.11358187e3,
+            3.00543684e2,
+            -9.94773230,
+            1.70276630e-1,
+            -1.43033468e-3,
+            4.73886084e-6,
+        ],
+    ),
+];
+
+const T_EMF: [Segment; 2] = [
+    (
+        0.0,
+        &[
+            0.0,
+            0.387481063640e-1,
+            0.441944343470e-4,
+            0.118443231050e-6,
+            0.200329735540e-7,
+            0.901380195590e-9,
+            0.226511565930e-10,
+            0.360711542050e-12,
+            0.384939398830e-14,
+            0.282135219250e-16,
+            0.142515947790e-18,
+            0.487686622860e-21,
+            0.107955392700e-23,
+            0.139450270620e-26,
+            0.797951539270e-30,
+        ],
+    ),
+    (
+        400.0,
+        &[
+            0.0,
+            0.387481063640e-1,
+            0.332922278800e-4,
+            0.206182434040e-6,
+            -0.218822568460e-8,
+            0.109968809280e-10,
+            -0.308157587720e-13,
+            0.454791352900e-16,
+            -0.275129016730e-19,
+        ],
+    ),
+];
+
+const T_TEMPERATURE: [Segment; 2] = [
+    (
+        0.0,
+        &[
+            0.0,
+            2.5949192e1,
+            -2.1316967e-1,
+            7.9018692e-1,
+            4.2527777e-1,
+            1.3304473e-1,
+            2.0241446e-2,
+            1.2668171e-3,
+        ],
+    ),
+    (
+        20.872,
+        &[
+            0.0,
+            2.592800e1,
+            -7.602961e-1,
+            4.637791e-2,
+            -2.165394e-3,
+            6.048144e-5,
+            -7.293422e-7,
+        ],
+    ),
+];
+
+const E_EMF: [Segment; 2] = [
+    (
+        0.0,
+        &[
+            0.0,
+            0.586655087080e-1,
+            0.454109771240e-4,
+            -0.779980486860e-6,
+            -0.258001608430e-7,
+            -0.594525830570e-9,
+            -0.932140586670e-11,
+            -0.102876055340e-12,
+            -0.803701236210e-15,
+            -0.439794973910e-17,
+            -0.164147763550e-19,
+            -0.396736195160e-22,
+            -0.558273287210e-25,
+            -0.346578420130e-28,
+        ],
+    ),
+    (
+        1000.0,
+        &[
+            0.0,
+            0.586655087100e-1,
+            0.450322755820e-4,
+            0.289084072120e-7,
+            -0.330568966520e-9,
+            0.650244032700e-12,
+            -0.191974955040e-15,
+            -0.125366004970e-17,
+            0.214892175690e-20,
+            -0.143880417820e-23,
+            0.359608994810e-27,
+        ],
+    ),
+];
+
+const E_TEMPERATURE: [Segment; 2] = [
+    (
+        0.0,
+        &[
+            0.0,
+            1.6977288e1,
+            -4.3514970e-1,
+            -1.5859697e-1,
+            -9.2502871e-2,
+            -2.6084314e-2,
+            -4.1360199e-3,
+            -3.4034030e-4,
+            -1.1564890e-5,
+        ],
+    ),
+    (
+        76.373,
+        &[
+            0.0,
+            1.7057035e1,
+            -2.3301759e-1,
+            6.5435585e-3,
+            -7.3562749e-5,
+            -1.7896001e-6,
+            8.4036165e-8,
+            -1.3735879e-9,
+            1.0629823e-11,
+            -3.2447087e-14,
+        ],
+    ),
+];
+
+const N_EMF: [Segment; 2] = [
+    (
+        0.0,
+        &[
+            0.0,
+            0.261591059620e-1,
+            0.109574842280e-4,
+            -0.938411115540e-7,
+            -0.464120397590e-10,
+            -0.263033577160e-11,
+            -0.226534380030e-13,
+            -0.760893007910e-16,
+            -0.934196678350e-19,
+        ],
+    ),
+    (
+        1300.0,
+        &[
+            0.0,
+            0.259293946010e-1,
+            0.157101418800e-4,
+            0.438256272370e-7,
+            -0.252611697940e-9,
+            0.643118193390e-12,
+            -0.100634715190e-14,
+            0.997453389920e-18,
+            -0.608632456070e-21,
+            0.208492293390e-24,
+            -0.306821961510e-28,
+        ],
+    ),
+];
+
+const N_TEMPERATURE: [Segment; 3] = [
+    (
+        0.0,
+        &[
+            0.0,
+            3.8436847e1,
+            1.1010485,
+            5.2229312,
+            7.2060525,
+            5.8488586,
+            2.7754916,
+            7.7075166e-1,
+            1.1582665e-1,
+            7.3138868e-3,
+        ],
+    ),
+    (
+        20.613,
+        &[
+            0.0,
+            3.86896e1,
+            -1.08267,
+            4.70205e-2,
+            -2.12169e-6,
+            -1.17272e-4,
+            5.39280e-6,
+            -7.98156e-8,
+        ],
+    ),
+    (
+        47.513,
+        &[
+            1.972485e1,
+            3.300943e1,
+            -3.915159e-1,
+            9.855391e-3,
+            -1.274371e-4,
+            7.767022e-7,
+        ],
+    ),
+];
+
+const R_EMF: [Segment; 3] = [
+    (
+        1064.18,
+        &[
+            0.0,
+            0.528961729765e-2,
+            0.139166589782e-4,
+            -0.238855693017e-7,
+            0.356916001063e-10,
+            -0.462347666298e-13,
+            0.500777441034e-16,
+            -0.373105886191e-19,
+            0.157716482367e-22,
+            -0.281038625251e-26,
+        ],
+    ),
+    (
+        1664.5,
+        &[
+            0.295157925316e1,
+            -0.252061251332e-2,
+            0.159564501865e-4,
+            -0.764085947576e-8,
+            0.205305291024e-11,
+            -0.293359668173e-15,
+        ],
+    ),
+    (
+        1768.1,
+        &[
+            0.152232118209e3,
+            -0.268819888545,
+            0.171280280471e-3,
+            -0.345895706453e-7,
+            -0.934633971046e-14,
+        ],
+    ),
+];
+
+const R_TEMPERATURE: [Segment; 4] = [
+    (
+        1.923,
+        &[
+            0.0,
+            1.8891380e2,
+            -9.3835290e1,
+            1.3068619e2,
+            -2.2703580e2,
+            3.5145659e2,
+            -3.8953900e2,
+            2.8239471e2,
+            -1.2607281e2,
+            3.1353611e1,
+            -3.3187769,
+        ],
+    ),
+    (
+        11.361,
+        &[
+            1.334584505e1,
+            1.472644573e2,
+            -1.844024844e1,
+            4.031129726,
+            -6.249428360e-1,
+            6.468412046e-2,
+            -4.458750426e-3,
+            1.994710149e-4,
+            -5.313401790e-6,
+            6.481976217e-8,
+        ],
+    ),
+    (
+        19.739,
+        &[
+            -8.199599416e1,
+            1.553962042e2,
+            -8.342197663,
+            4.279433549e-1,
+            -1.191577910e-2,
+            1.492290091e-4,
+        ],
+    ),
+    (
+        21.103,
+        &[
+            3.406177836e4,
+            -7.023729171e3,
+            5.582903813e2,
+            -1.952394635e1,
+            2.560740231e-1,
+        ],
+    ),
+];
+
+const S_EMF: [Segment; 3] = [
+    (
+        1064.18,
+        &[
+            0.0,
+            0.540313308631e-2,
+            0.125934289740e-4,
+            -0.232477968689e-7,
+            0.322028823036e-10,
+            -0.331465196389e-13,
+            0.255744251786e-16,
+            -0.125068871393e-19,
+            0.271443176145e-23,
+        ],
+    ),
+    (
+        1664.5,
+        &[
+            0.132900444085e1,
+            0.334509311344e-2,
+            0.654805192818e-5,
+            -0.164856259209e-8,
+            0.129989605174e-13,
+        ],
+    ),
+    (
+        1768.1,
+        &[
+            0.146628232636e3,
+            -0.258430516752,
+            0.163693574641e-3,
+            -0.330439046987e-7,
+            -0.943223690612e-14,
+        ],
+    ),
+];
+
+const S_TEMPERATURE: [Segment; 4] = [
+    (
+        1.874,
+        &[
+            0.0,
+            1.84949460e2,
+            -8.00504062e1,
+            1.02237430e2,
+            -1.52248592e2,
+            1.88821343e2,
+            -1.59085941e2,
+            8.23027880e1,
+            -2.34181944e1,
+            2.79786260,
+        ],
+    ),
+    (
+        10.332,
+        &[
+            1.291507177e1,
+            1.466298863e2,
+            -1.534713402e1,
+            3.145945973,
+            -4.163257839e-1,
+            3.187963771e-2,
+            -1.291637500e-3,
+            2.183475087e-5,
+            -1.447379511e-7,
+            8.211272125e-9,
+        ],
+    ),
+    (
+        17.536,
+        &[
+            -8.087801117e1,
+            1.621573104e2,
+            -8.536869453,
+            4.719686976e-1,
+            -1.441693666e-2,
+            2.081618890e-4,
+        ],
+    ),
+    (
+        18.693,
+        &[
+            5.333875126e4,
+            -1.235892298e4,
+            1.092657613e3,
+            -4.265693686e1,
+            6.247205420e-1,
+        ],
+    ),
+];
+
+const B_EMF: [Segment; 2] = [
+    (
+        630.615,
+        &[
+            0.0,
+            -0.246508183460e-3,
+            0.590404211710e-5,
+            -0.132579316360e-8,
+            0.156682919010e-11,
+            -0.169445292400e-14,
+            0.629903470940e-18,
+        ],
+    ),
+    (
+        1820.0,
+        &[
+            -0.389381686210e1,
+            0.285717474700e-1,
+            -0.848851047850e-4,
+            0.157852801640e-6,
+            -0.168353448640e-9,
+            0.111097940130e-12,
+            -0.445154310330e-16,
+            0.989756408210e-20,
+            -0.937913302890e-24,
+        ],
+    ),
+];
+
+const B_TEMPERATURE: [Segment; 2] = [
+    (
+        2.431,
+        &[
+            9.8423321e1,
+            6.9971500e2,
+            -8.4765304e2,
+            1.0052644e3,
+            -8.3345952e2,
+            4.5508542e2,
+            -1.5523037e2,
+            2.9886750e1,
+            -2.4742860,
+        ],
+    ),
+    (
+        13.820,
+        &[
+            2.1315071e2,
+            2.8510504e2,
+            -5.2742887e1,
+            9.9160804,
+            -1.2965303,
+            1.1195870e-1,
+            -6.0625199e-3,
+            1.8661696e-4,
+            -2.4878585e-6,
+        ],
+    ),
+];
+
+/// Evaluate the polynomial of the segment covering `x`, the outermost segments are used
+/// beyond the specified range
+fn evaluate(segments: &[Segment], x: f64) -> f64 {
+    let (_, coefficients) = segments
+        .iter()
+        .find(|(limit, _)| x <= *limit)
+        .unwrap_or(&segments[segments.len() - 1]);
+    coefficients.iter().rev().fold(0.0, |acc, c| acc * x + c)
+}
+
+/// Thermocouple type according to IEC 60584
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum ThermocoupleType {
+    /// Chromel–Alumel, -270 °C to 1372 °C
+    K,
+    /// Iron–Constantan, -210 °C to 1200 °C
+    J,
+    /// Copper–Constantan, -270 °C to 400 °C
+    T,
+    /// Chromel–Constantan, -270 °C to 1000 °C
+    E,
+    /// Nicrosil–Nisil, -270 °C to 1300 °C
+    N,
+    /// Platinum-13% Rhodium–Platinum, -50 °C to 1768 °C
+    R,
+    /// Platinum-10% Rhodium–Platinum, -50 °C to 1768 °C
+    S,
+    /// Platinum-30% Rhodium–Platinum-6% Rhodium, 0 °C to 1820 °C
+    B,
+}
+
+impl ThermocoupleType {
+    /// Thermoelectric voltage in mV at the given temperature in °C with the reference junction
+    /// at 0 °C (NIST ITS-90 reference function)
+    pub fn emf(&self, celsius: f32) -> f32 {
+        let t = celsius as f64;
+        let emf = match self {
+            ThermocoupleType::K => {
+                let [a0, a1, a2] = K_EMF_EXPONENTIAL;
+                let exponential = if t > 0.0 {
+                    a0 * libm::exp(a1 * (t - a2) * (t - a2))
+                } else {
+                    0.0
+                };
+                evaluate(&K_EMF, t) + exponential
+            }
+            ThermocoupleType::J => evaluate(&J_EMF, t),
+            ThermocoupleType::T => evaluate(&T_EMF, t),
+            ThermocoupleType::E => evaluate(&E_EMF, t),
+            ThermocoupleType::N => evaluate(&N_EMF, t),
+            ThermocoupleType::R => evaluate(&R_EMF, t),
+            ThermocoupleType::S => evaluate(&S_EMF, t),
+            ThermocoupleType::B => evaluate(&B_EMF, t),
+        };
+        emf as f32
+    }
+
+    /// Temperature in °C at the given thermoelectric voltage in mV with the reference junction
+    /// at 0 °C (NIST ITS-90 inverse polynomials). The inverse polynomials cover a smaller
+    /// range than [`emf`](Self::emf), e.g. -200 °C upwards for types K, T, E and N and 250 °C
+    /// upwards for type B.
+    pub fn temperature(&self, emf: f32) -> f32 {
+        let e = emf as f64;
+        let temperature = match self {
+            ThermocoupleType::K => evaluate(&K_TEMPERATURE, e),
+            ThermocoupleType::J => evaluate(&J_TEMPERATURE, e),
+            ThermocoupleType::T => evaluate(&T_TEMPERATURE, e),
+            ThermocoupleType::E => evaluate(&E_TEMPERATURE, e),
+            ThermocoupleType::N => evaluate(&N_TEMPERATURE, e),
+            ThermocoupleType::R => evaluate(&R_TEMPERATURE, e),
+            ThermocoupleType::S => evaluate(&S_TEMPERATURE, e),
+            ThermocoupleType::B => evaluate(&B_TEMPERATURE, e),
+        };
+        temperature as f32
+    }
+}
+
+/// Result of a thermocouple measurement
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ThermocoupleMeasurement {
+    /// compensated hot-junction temperature in °C
+    pub temperature: f32,
+    /// cold-junction temperature measured by the internal temperature sensor in °C
+    pub cold_junction: f32,
+    /// measured thermocouple voltage in mV
+    pub emf: f32,
+}
+
+/// Thermocouple connected to the ADC
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Thermocouple {
+    /// type of the thermocouple
+    pub kind: ThermocoupleType,
+    /// differential inputs across the thermocouple (MUX)
+    pub mux: Mux,
+    /// gain of the PGA (GAIN), e.g. 32 for ±64 mV with the internal reference
+    pub gain: Gain,
+}
+
+impl Thermocouple {
+    /// Configuration measuring the thermocouple voltage, keeping all other settings of `config`
+    pub fn config(&self, config: &Config) -> Config {
+        Config {
+            v_ref: VRef::Internal,
+            gain: self.gain,
+            mux: self.mux,
+            pga_bypass: false,
+            temperature_sensor_mode: false,
+            ..*config
+        }
+    }
+
+    /// Configure the device, measure the cold junction with the internal temperature sensor and
+    /// the thermocouple voltage, and return the compensated hot-junction temperature.
+    /// A saturated result, e.g. of an open thermocouple with the burn-out current sources
+    /// enabled, is reported as [`Error::OpenInput`].
+    pub fn measure<BUS, DRDY, E>(
+        &self,
+        adc: &mut ADS122x04<BUS, DRDY>,
+    ) -> Result<ThermocoupleMeasurement, Error<E>>
+    where
+        BUS: ReadData<Error = Error<E>> + WriteData<Error = Error<E>>,
+        DRDY: InputPin,
+    {
+        let config = self.config(adc.config());
+        adc.apply_config(&config)?;
+        let cold_junction = adc.get_temperature_celsius()?;
+        let sample = adc.measure_sensor(&config, SensorLimits::Bipolar)?;
+        let emf = sample.to_voltage() * 1000.0;
+        Ok(ThermocoupleMeasurement {
+            temperature: self.kind.temperature(emf + self.kind.emf(cold_junction)),
+            cold_junction,
+            emf,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// NIST ITS-90 table values as (°C, mV), at least one on each segment of the reference
+    /// function and of the inverse polynomial
+    const TABLES: [(ThermocoupleType, &[(f32, f32)]); 8] = [
+        (
+            ThermocoupleType::K,
+            &[
+                (-200.0, -5.891),
+                (-100.0, -3.554),
+                (100.0, 4.096),
+                (300.0, 12.209),
+                (500.0, 20.644),
+                (800.0, 33.275),
+                (1000.0, 41.276),
+                (1300.0, 52.410),
+            ],
+        ),
+        (
+            ThermocoupleType::J,
+            &[
+                (-200.0, -7.890),
+                (-100.0, -4.633),
+                (100.0, 5.269),
+                (500.0, 27.393),
+                (700.0, 39.132),
+                (800.0, 45.494),
+                (1000.0, 57.953),
+            ],
+        ),
+        (
+            ThermocoupleType::T,
+            &[
+                (-200.0, -5.603),
+                (-100.0, -3.379),
+                (100.0, 4.279),
+                (200.0, 9.288),
+                (300.0, 14.862),
+                (400.0, 20.872),
+            ],
+        ),
+        (
+            ThermocoupleType::E,
+            &[
+                (-200.0, -8.825),
+                (-100.0, -5.237),
+                (100.0, 6.319),
+                (500.0, 37.005),
+                (1000.0, 76.373),
+            ],
+        ),
+        (
+            ThermocoupleType::N,
+            &[
+                (-200.0, -3.990),
+                (-100.0, -2.407),
+                (100.0, 2.774),
+                (500.0, 16.748),
+                (1000.0, 36.256),
+                (1300.0, 47.513),
+            ],
+        ),
+        (
+            ThermocoupleType::R,
+            &[
+                (100.0, 0.647),
+                (200.0, 1.469),
+                (500.0, 4.471),
+                (1000.0, 10.506),
+                (1200.0, 13.228),
+                (1500.0, 17.451),
+                (1700.0, 20.222),
+            ],
+        ),
+        (
+            ThermocoupleType::S,
+            &[
+                (100.0, 0.646),
+                (200.0, 1.441),
+                (500.0, 4.233),
+                (1000.0, 9.587),
+                (1200.0, 11.951),
+                (1500.0, 15.582),
+                (1700.0, 17.947),
+            ],
+        ),
+        (
+            ThermocoupleType::B,
+            &[
+                (300.0, 0.431),
+                (500.0, 1.242),
+                (1000.0, 4.834),
+                (1500.0, 10.099),
+                (1800.0, 13.591),
+            ],
+        ),
+    ];
+
+    #[test]
+    fn reference_functions() {
+        for (kind, table) in TABLES {
+            for &(celsius, emf) in table {
+                let actual = kind.emf(celsius);
+                assert!(
+                    (actual - emf).abs() < 1e-3,
+                    "{kind:?} at {celsius} °C: {actual} mV instead of {emf} mV"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn inverse_polynomials() {
+        for (kind, table) in TABLES {
+            for &(celsius, emf) in table {
+                // the table is rounded to 1 µV, which is up to 0.2 °C at low sensitivities
+                let sensitivity = (kind.emf(celsius + 1.0) - kind.emf(celsius - 1.0)) / 2.0;
+                let tolerance = 0.06 + 0.0005 / sensitivity;
+                let actual = kind.temperature(emf);
+                assert!(
+                    (actual - celsius).abs() < tolerance,
+                    "{kind:?} at {emf} mV: {actual} °C instead of {celsius} °C"
+                );
+                let actual = kind.temperature(kind.emf(celsius));
+                assert!(
+                    (actual - celsius).abs() < 0.06,
+                    "{kind:?} at {celsius} °C: round trip to {actual} °C"
+                );
+            }
+        }
+    }
+}
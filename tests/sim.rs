@@ -0,0 +1,786 @@
+//! End-to-end tests of the driver against the simulated device on I2C and UART
+#![cfg(feature = "sim")]
+
+use core::cell::RefCell;
+use core::convert::Infallible;
+use std::rc::Rc;
+
+use ads122x04::address::{self, Address, AddressPin};
+use ads122x04::bridge::Bridge;
+use ads122x04::calibration::CalibrationError;
+use ads122x04::gpio::{Gpio, GpioPin};
+use ads122x04::interface::{ReadData, WriteData};
+use ads122x04::registers::*;
+use ads122x04::rtd::{Rtd, RtdSensor, Wiring};
+use ads122x04::scan::{Channel, Scanner};
+use ads122x04::sim::{SimError, Simulator};
+use ads122x04::thermocouple::{Thermocouple, ThermocoupleType};
+use ads122x04::{ADS122x04, Continuity, Error};
+use embedded_hal::digital::{InputPin, OutputPin};
+use embedded_hal::{digital, i2c};
+
+const ADDRESS: u8 = 0x40;
+
+/// Simulator shared between the driver and the test, to change the device behind its back
+#[derive(Clone)]
+struct Shared(Rc<RefCell<Simulator>>);
+
+impl Shared {
+    fn new(sim: Simulator) -> Self {
+        Shared(Rc::new(RefCell::new(sim)))
+    }
+}
+
+impl i2c::ErrorType for Shared {
+    type Error = SimError;
+}
+
+impl i2c::I2c for Shared {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.0.borrow_mut().transaction(address, operations)
+    }
+}
+
+impl embedded_io::ErrorType for Shared {
+    type Error = SimError;
+}
+
+impl embedded_io::Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        embedded_io::Write::write(&mut *self.0.borrow_mut(), buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_io::Read for Shared {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io::Read::read(&mut *self.0.borrow_mut(), buf)
+    }
+}
+
+/// DRDY pin that never signals a finished conversion
+struct StuckHigh;
+
+impl digital::ErrorType for StuckHigh {
+    type Error = Infallible;
+}
+
+impl digital::InputPin for StuckHigh {
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(true)
+    }
+
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(false)
+    }
+}
+
+/// Bus that fails every RDATA command and forwards everything else to the simulator
+struct FailingRdata<'a>(&'a mut Simulator);
+
+impl i2c::ErrorType for FailingRdata<'_> {
+    type Error = SimError;
+}
+
+impl i2c::I2c for FailingRdata<'_> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter() {
+            if let i2c::Operation::Write([command, ..]) = operation {
+                if command & 0xF0 == 0x10 {
+                    return Err(SimError::InvalidCommand(*command));
+                }
+            }
+        }
+        self.0.transaction(address, operations)
+    }
+}
+
+impl embedded_io::ErrorType for FailingRdata<'_> {
+    type Error = SimError;
+}
+
+impl embedded_io::Write for FailingRdata<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if let [0x55, command, ..] = buf {
+            if command & 0xF0 == 0x10 {
+                return Err(SimError::InvalidCommand(*command));
+            }
+        }
+        embedded_io::Write::write(self.0, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_io::Read for FailingRdata<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io::Read::read(self.0, buf)
+    }
+}
+
+/// UART losing a single byte of the conversion data sent in automatic data read mode
+struct LossyUart<'a> {
+    sim: &'a mut Simulator,
+    /// bytes received in automatic data read mode
+    received: usize,
+    /// index of the lost byte
+    lost: usize,
+}
+
+impl embedded_io::ErrorType for LossyUart<'_> {
+    type Error = SimError;
+}
+
+impl embedded_io::Write for LossyUart<'_> {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        embedded_io::Write::write(self.sim, buf)
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+impl embedded_io::Read for LossyUart<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        let Some(first) = buf.first_mut() else {
+            return Ok(0);
+        };
+        let mut byte = [0];
+        loop {
+            let auto_read = self.sim.register(3) & 0x01 != 0;
+            if embedded_io::Read::read(self.sim, &mut byte)? == 0 {
+                return Ok(0);
+            }
+            if !auto_read {
+                break;
+            }
+            self.received += 1;
+            if self.received - 1 != self.lost {
+                break;
+            }
+        }
+        *first = byte[0];
+        Ok(1)
+    }
+}
+
+/// I2C bus logging the config registers written with WREG
+struct WriteLog<'a> {
+    sim: &'a mut Simulator,
+    registers: Vec<u8>,
+}
+
+impl i2c::ErrorType for WriteLog<'_> {
+    type Error = SimError;
+}
+
+impl i2c::I2c for WriteLog<'_> {
+    fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [i2c::Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        for operation in operations.iter() {
+            if let i2c::Operation::Write([command, ..]) = operation {
+                if command & 0xF0 == 0x40 {
+                    self.registers.push((command >> 2) & 0b11);
+                }
+            }
+        }
+        self.sim.transaction(address, operations)
+    }
+}
+
+fn check_registers<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.set_verify_writes(true);
+    // 0x40 is also the WREG command byte on the ADS122U04
+    adc.set_input_mux(Mux::Ain1Ain2).unwrap();
+    adc.set_gain(Gain::Gain32).unwrap();
+    adc.set_data_rate(DataRate::Sps600Normal).unwrap();
+    adc.set_current_level(CurrentSource::I250uA).unwrap();
+    adc.set_current_route_1(CurrentRoute::Ain3).unwrap();
+    adc.set_current_route_2(CurrentRoute::RefP).unwrap();
+    assert_eq!(adc.get_input_mux().unwrap(), Mux::Ain1Ain2);
+    assert_eq!(adc.get_gain().unwrap(), Gain::Gain32);
+    assert_eq!(adc.get_data_rate().unwrap(), DataRate::Sps600Normal);
+    assert_eq!(adc.get_current_level().unwrap(), CurrentSource::I250uA);
+    assert_eq!(adc.get_current_route_1().unwrap(), CurrentRoute::Ain3);
+    assert_eq!(adc.get_current_route_2().unwrap(), CurrentRoute::RefP);
+    assert_eq!(adc.read_config().unwrap(), *adc.config());
+}
+
+#[test]
+fn registers_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    check_registers(&mut ADS122x04::new_i2c(ADDRESS, &mut sim));
+    assert_eq!(sim.register(0), 0x4A);
+}
+
+#[test]
+fn registers_uart() {
+    let mut sim = Simulator::new_serial();
+    check_registers(&mut ADS122x04::new_serial(&mut sim));
+    assert_eq!(sim.register(0), 0x4A);
+}
+
+fn check_data<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.start().unwrap();
+    adc.wait_for_data().unwrap();
+    assert_eq!(adc.get_raw_adc().unwrap(), -1234);
+}
+
+#[test]
+fn data_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_result(-1234);
+    check_data(&mut ADS122x04::new_i2c(ADDRESS, &mut sim));
+}
+
+#[test]
+fn data_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(-1234);
+    check_data(&mut ADS122x04::new_serial(&mut sim));
+}
+
+fn check_integrity<BUS>(adc: &mut ADS122x04<BUS>, crc: Crc)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.set_verify_writes(true);
+    adc.set_crc(crc).unwrap();
+    adc.set_gain(Gain::Gain4).unwrap();
+    assert_eq!(adc.get_crc().unwrap(), crc);
+    assert_eq!(adc.get_gain().unwrap(), Gain::Gain4);
+    check_data(adc);
+}
+
+#[test]
+fn crc16_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_result(-1234);
+    check_integrity(&mut ADS122x04::new_i2c(ADDRESS, &mut sim), Crc::Crc16);
+    assert_eq!((sim.register(2) >> 4) & 0b11, Crc::Crc16 as u8);
+}
+
+#[test]
+fn crc16_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(-1234);
+    check_integrity(&mut ADS122x04::new_serial(&mut sim), Crc::Crc16);
+    assert_eq!((sim.register(2) >> 4) & 0b11, Crc::Crc16 as u8);
+}
+
+#[test]
+fn inverted_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_result(-1234);
+    check_integrity(&mut ADS122x04::new_i2c(ADDRESS, &mut sim), Crc::Inverted);
+    assert_eq!((sim.register(2) >> 4) & 0b11, Crc::Inverted as u8);
+}
+
+#[test]
+fn inverted_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(-1234);
+    check_integrity(&mut ADS122x04::new_serial(&mut sim), Crc::Inverted);
+    assert_eq!((sim.register(2) >> 4) & 0b11, Crc::Inverted as u8);
+}
+
+fn check_data_counter<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.set_data_counter(true).unwrap();
+    adc.set_crc(Crc::Crc16).unwrap();
+    adc.set_conversion_mode(ConversionMode::Continuous).unwrap();
+    adc.start().unwrap();
+    let first = adc.read_sample().unwrap();
+    assert_eq!(first.continuity, Continuity::Unknown);
+    let second = adc.read_sample().unwrap();
+    assert_eq!(second.counter, first.counter.map(|counter| counter + 1));
+    assert_eq!(second.continuity, Continuity::Consecutive);
+    assert_eq!(second.raw, 42);
+}
+
+#[test]
+fn data_counter_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_result(42);
+    check_data_counter(&mut ADS122x04::new_i2c(ADDRESS, &mut sim));
+}
+
+#[test]
+fn data_counter_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(42);
+    check_data_counter(&mut ADS122x04::new_serial(&mut sim));
+}
+
+fn check_reset<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.set_gain(Gain::Gain128).unwrap();
+    adc.set_crc(Crc::Crc16).unwrap();
+    adc.reset().unwrap();
+    assert_eq!(adc.config().gain, Gain::Gain1);
+    assert_eq!(adc.read_config().unwrap(), *adc.config());
+}
+
+#[test]
+fn reset_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    check_reset(&mut ADS122x04::new_i2c(ADDRESS, &mut sim));
+    assert_eq!(sim.register(0), 0);
+    assert_eq!(sim.register(2), 0);
+}
+
+#[test]
+fn reset_uart() {
+    let mut sim = Simulator::new_serial();
+    check_reset(&mut ADS122x04::new_serial(&mut sim));
+    assert_eq!(sim.register(0), 0);
+    assert_eq!(sim.register(2), 0);
+}
+
+fn check_power_down<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    let sample = adc.sample_and_sleep().unwrap();
+    assert_eq!(sample.raw, -1234);
+    assert!(adc.is_powered_down());
+}
+
+#[test]
+fn power_down_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_result(-1234);
+    check_power_down(&mut ADS122x04::new_i2c(ADDRESS, &mut sim));
+    assert!(sim.is_powered_down());
+    assert_eq!(sim.conversions(), 1);
+}
+
+#[test]
+fn power_down_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(-1234);
+    check_power_down(&mut ADS122x04::new_serial(&mut sim));
+    assert!(sim.is_powered_down());
+    assert_eq!(sim.conversions(), 1);
+}
+
+fn check_temperature<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    assert_eq!(adc.get_temperature_celsius(), Ok(-25.0));
+    assert!(!adc.config().temperature_sensor_mode);
+    // the mode is kept if it was enabled before
+    adc.set_temperature_sensor_mode(true).unwrap();
+    assert_eq!(adc.get_temperature_celsius(), Ok(-25.0));
+    assert!(adc.config().temperature_sensor_mode);
+}
+
+#[test]
+fn temperature_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    // 14-bit result of -800 * 0.03125 °C, left-justified in the 24-bit data
+    sim.set_result(-800 << 10);
+    check_temperature(&mut ADS122x04::new_i2c(ADDRESS, &mut sim));
+}
+
+#[test]
+fn temperature_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(-800 << 10);
+    check_temperature(&mut ADS122x04::new_serial(&mut sim));
+}
+
+fn check_temperature_error<BUS>(adc: &mut ADS122x04<BUS>)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    assert_eq!(
+        adc.get_temperature_celsius(),
+        Err(Error::CommError(SimError::InvalidCommand(0x10)))
+    );
+    assert!(!adc.config().temperature_sensor_mode);
+    assert_eq!(adc.shadow().dirty_registers().count(), 0);
+}
+
+#[test]
+fn temperature_error_i2c() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    check_temperature_error(&mut ADS122x04::new_i2c(ADDRESS, FailingRdata(&mut sim)));
+    assert_eq!(sim.register(1) & 0b1, 0);
+}
+
+#[test]
+fn temperature_error_uart() {
+    let mut sim = Simulator::new_serial();
+    check_temperature_error(&mut ADS122x04::new_serial(FailingRdata(&mut sim)));
+    assert_eq!(sim.register(1) & 0b1, 0);
+}
+
+fn check_resync<BUS>(adc: &mut ADS122x04<BUS>, sim: &Shared)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.set_crc(Crc::Crc16).unwrap();
+    adc.set_gain(Gain::Gain8).unwrap();
+    // brown-out: the device returns to its power-on defaults
+    for reg in 0..4 {
+        sim.0.borrow_mut().set_register(reg, 0);
+    }
+    adc.resync().unwrap();
+    assert_eq!(adc.config().crc, Crc::Disabled);
+    assert_eq!(adc.config().gain, Gain::Gain1);
+}
+
+#[test]
+fn resync_crc_i2c() {
+    let sim = Shared::new(Simulator::new_i2c(ADDRESS));
+    check_resync(&mut ADS122x04::new_i2c(ADDRESS, sim.clone()), &sim);
+}
+
+#[test]
+fn resync_crc_uart() {
+    let sim = Shared::new(Simulator::new_serial());
+    check_resync(&mut ADS122x04::new_serial(sim.clone()), &sim);
+}
+
+fn check_measure_nb<BUS>(adc: &mut ADS122x04<BUS>, sim: &Shared)
+where
+    BUS: ReadData<Error = Error<SimError>> + WriteData<Error = Error<SimError>>,
+{
+    adc.set_conversion_mode(ConversionMode::Continuous).unwrap();
+    assert_eq!(adc.measure_nb(Mux::Ain2Avss), Err(nb::Error::WouldBlock));
+    assert_eq!(adc.config().conversion_mode, ConversionMode::SingleShot);
+    // the conversion is still running
+    let config2 = sim.0.borrow().register(2);
+    sim.0.borrow_mut().set_register(2, config2 & 0x7F);
+    assert_eq!(adc.measure_nb(Mux::Ain2Avss), Err(nb::Error::WouldBlock));
+    sim.0.borrow_mut().set_register(2, config2);
+    assert_eq!(adc.measure_nb(Mux::Ain2Avss), Ok(2000));
+    // the next call starts a new measurement
+    assert_eq!(adc.measure_nb(Mux::Ain3Avss), Err(nb::Error::WouldBlock));
+    assert_eq!(adc.measure_nb(Mux::Ain3Avss), Ok(3000));
+    let sim = sim.0.borrow();
+    assert_eq!(sim.mux(), Ok(Mux::Ain3Avss));
+    assert_eq!(
+        (sim.register(1) >> 3) & 0b1,
+        ConversionMode::SingleShot as u8
+    );
+    assert_eq!(sim.conversions(), 2);
+}
+
+fn conversion_by_input(sim: &Simulator) -> i32 {
+    match sim.mux() {
+        Ok(Mux::Ain2Avss) => 2000,
+        Ok(Mux::Ain3Avss) => 3000,
+        _ => 0,
+    }
+}
+
+#[test]
+fn measure_nb_i2c() {
+    let sim = Shared::new(Simulator::new_i2c(ADDRESS));
+    sim.0.borrow_mut().set_conversion(conversion_by_input);
+    check_measure_nb(&mut ADS122x04::new_i2c(ADDRESS, sim.clone()), &sim);
+}
+
+#[test]
+fn measure_nb_uart() {
+    let sim = Shared::new(Simulator::new_serial());
+    sim.0.borrow_mut().set_conversion(conversion_by_input);
+    check_measure_nb(&mut ADS122x04::new_serial(sim.clone()), &sim);
+}
+
+#[test]
+fn drdy_pin_timeout() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim).with_drdy_timeout(StuckHigh, 1000);
+    assert_eq!(adc.wait_for_data(), Err(Error::Timeout));
+}
+
+#[test]
+fn calibrate_offset_keeps_fallback() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_result(7);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim);
+    adc.offset = 500;
+    adc.set_gain(Gain::Gain16).unwrap();
+    adc.calibrate_offset().unwrap();
+    assert_eq!(adc.offset, 500);
+    assert_eq!(adc.active_offset(), 7);
+    adc.set_gain(Gain::Gain1).unwrap();
+    assert_eq!(adc.active_offset(), 500);
+    assert_eq!(adc.get_raw_adc().unwrap(), 7 - 500);
+}
+
+#[test]
+fn bridge_tare_keeps_offset_calibration() {
+    let mut bridge = Bridge::new(Mux::Ain1Ain2, Gain::Gain128, 3.3);
+    let sim = Shared::new(Simulator::new_i2c(ADDRESS));
+    sim.0.borrow_mut().set_result(7);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, sim.clone());
+    bridge.configure(&mut adc).unwrap();
+    adc.calibrate_offset().unwrap();
+    assert_eq!(adc.active_offset(), 7);
+
+    sim.0.borrow_mut().set_result(7 + 2000);
+    bridge.tare(&mut adc, 4).unwrap();
+    assert_eq!(bridge.zero, 2000);
+    assert_eq!(adc.active_offset(), 7);
+    assert_eq!(bridge.read_mv_per_v(&mut adc), Ok(0.0));
+
+    // 1/8 of the full scale at gain 128 is 0.9765625 mV/V
+    sim.0.borrow_mut().set_result(7 + 2000 + (1 << 20));
+    assert_eq!(bridge.read_mv_per_v(&mut adc), Ok(0.9765625));
+    bridge.calibrate_span(&mut adc, 5.0, 4).unwrap();
+    assert!((bridge.read(&mut adc).unwrap() - 5.0).abs() < 1e-5);
+}
+
+#[test]
+fn gpio_output_level_uart() {
+    let mut sim = Simulator::new_serial();
+    let mut adc = ADS122x04::new_serial(&mut sim);
+    adc.set_gpio_direction(Gpio::Gpio1, true).unwrap();
+    adc.set_gpio_level(Gpio::Gpio1, true).unwrap();
+    assert!(adc.get_gpio_level(Gpio::Gpio1).unwrap());
+    // GPIO1DIR and GPIO1DAT
+    assert_eq!(sim.register(4), 0x22);
+
+    {
+        let adc = RefCell::new(ADS122x04::new_serial(&mut sim));
+        adc.borrow_mut()
+            .set_gpio_direction(Gpio::Gpio0, true)
+            .unwrap();
+        let mut pin = GpioPin::new(&adc, Gpio::Gpio0);
+        pin.set_high().unwrap();
+        assert!(pin.is_high().unwrap());
+        pin.set_low().unwrap();
+        assert!(pin.is_low().unwrap());
+    }
+    assert_eq!(sim.register(4), 0x10);
+}
+
+#[test]
+fn gpio_input_level_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_gpio_levels(0b101);
+    let adc = RefCell::new(ADS122x04::new_serial(&mut sim));
+    assert!(adc.borrow_mut().get_gpio_level(Gpio::Gpio0).unwrap());
+    assert!(!adc.borrow_mut().get_gpio_level(Gpio::Gpio1).unwrap());
+    let mut pin = GpioPin::new(&adc, Gpio::Gpio2);
+    assert!(pin.is_high().unwrap());
+    // an output reads back its own data instead of the applied level
+    adc.borrow_mut()
+        .set_gpio_direction(Gpio::Gpio2, true)
+        .unwrap();
+    assert!(pin.is_low().unwrap());
+}
+
+#[test]
+fn gpio_verify_writes_with_inputs_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_gpio_levels(0b011);
+    let mut adc = ADS122x04::new_serial(&mut sim);
+    adc.set_verify_writes(true);
+    // the data bits of the inputs GPIO0 and GPIO1 read back differently than written
+    adc.set_gpio_direction(Gpio::Gpio2, true).unwrap();
+    adc.set_gpio_level(Gpio::Gpio2, true).unwrap();
+    adc.set_gpio_level(Gpio::Gpio2, false).unwrap();
+    assert_eq!(
+        adc.get_gpio_config().unwrap().gpio_data,
+        [true, true, false]
+    );
+}
+
+#[test]
+fn auto_read_resync_uart() {
+    let mut sim = Simulator::new_serial();
+    sim.set_result(42);
+    // the second byte of the second frame
+    let mut uart = LossyUart {
+        sim: &mut sim,
+        received: 0,
+        lost: 7,
+    };
+    let mut adc = ADS122x04::new_serial(&mut uart);
+    adc.set_data_counter(true).unwrap();
+    adc.set_crc(Crc::Crc16).unwrap();
+    let mut stream = adc.start_auto_read().unwrap();
+    let first = stream.read_sample().unwrap();
+    assert_eq!(first.continuity, Continuity::Unknown);
+    // the damaged second frame is discarded and the stream realigns to the third
+    let third = stream.read_sample().unwrap();
+    assert_eq!(third.raw, 42);
+    assert_eq!(third.counter, first.counter.map(|counter| counter + 2));
+    assert_eq!(third.continuity, Continuity::Skipped(1));
+    let fourth = stream.read_sample().unwrap();
+    assert_eq!(fourth.raw, 42);
+    assert_eq!(fourth.continuity, Continuity::Consecutive);
+    stream.stop().unwrap();
+    assert_eq!(uart.received, 4 * 6);
+}
+
+#[test]
+fn address_scan() {
+    let address = Address::new(AddressPin::Sda, AddressPin::Dvdd);
+    let mut sim = Simulator::new_i2c(address.into());
+    assert_eq!(address::scan(&mut sim).collect::<Vec<_>>(), [address]);
+    let mut adc = ADS122x04::new_i2c_with_address(address, &mut sim);
+    adc.set_input_mux(Mux::Ain2Avss).unwrap();
+    assert_eq!(sim.mux(), Ok(Mux::Ain2Avss));
+
+    // a device answering with an invalid input multiplexer is not an ADS122C04
+    sim.set_register(0, 0xF0);
+    assert_eq!(address::scan(&mut sim).count(), 0);
+}
+
+#[test]
+fn gain_correction_validated() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim);
+    adc.set_gain_correction(Gain::Gain2, 1.01).unwrap();
+    for factor in [-1.0, 0.0, f32::NAN, f32::INFINITY] {
+        assert_eq!(
+            adc.set_gain_correction(Gain::Gain2, factor),
+            Err(CalibrationError::InvalidFactor)
+        );
+    }
+    assert_eq!(adc.gain_correction(Gain::Gain2), 1.01);
+    let mut data = adc.calibration_data();
+    data.gain_correction[Gain::Gain4 as usize] = -1.0;
+    assert_eq!(
+        adc.set_calibration_data(&data),
+        Err(CalibrationError::InvalidFactor)
+    );
+    assert_eq!(adc.gain_correction(Gain::Gain4), 1.0);
+}
+
+#[test]
+fn scan_channels() {
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_conversion(|sim| match sim.mux() {
+        Ok(Mux::Ain0Avss) => 100,
+        Ok(Mux::Ain1Avss) => 200,
+        Ok(Mux::Ain2Avss) => 300,
+        _ => 0,
+    });
+    let mut bus = WriteLog {
+        sim: &mut sim,
+        registers: Vec::new(),
+    };
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut bus);
+    let fast = Channel {
+        gain: Gain::Gain4,
+        data_rate: DataRate::Sps90Normal,
+        ..Channel::new(Mux::Ain1Avss)
+    };
+    let scanner = Scanner::new([
+        Channel::new(Mux::Ain0Avss),
+        Channel { discard: 2, ..fast },
+        Channel {
+            mux: Mux::Ain2Avss,
+            ..fast
+        },
+    ]);
+    let samples = scanner.scan(&mut adc).unwrap();
+    assert_eq!(samples.map(|sample| sample.raw), [100, 200, 300]);
+    assert_eq!(
+        samples.map(|sample| sample.gain),
+        [Gain::Gain1, Gain::Gain4, Gain::Gain4]
+    );
+    assert_eq!(adc.config().mux, Mux::Ain2Avss);
+    // the mux of every channel, the data rate only when switching to the second channel
+    assert_eq!(bus.registers, [0, 0, 1, 0]);
+    // one conversion per channel and two discarded ones
+    assert_eq!(sim.conversions(), 5);
+}
+
+#[test]
+fn rtd_measure() {
+    let rtd = Rtd {
+        sensor: RtdSensor::Pt100,
+        wiring: Wiring::ThreeWire,
+        reference_resistance: 1620.0,
+        mux: Mux::Ain1Ain0,
+        gain: Gain::Gain4,
+        current: CurrentSource::I500uA,
+        current_route_1: CurrentRoute::Ain2,
+        current_route_2: CurrentRoute::Ain3,
+    };
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    // 138.506 Ω at 100 °C against both currents through the reference resistor
+    let raw = 138.506 / (2.0 * 1620.0) * 4.0 * (1 << 23) as f64;
+    sim.set_result(raw.round() as i32);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim);
+    let measurement = rtd.measure(&mut adc).unwrap();
+    assert!((measurement.resistance - 138.506).abs() < 1e-3);
+    assert!((measurement.temperature - 100.0).abs() < 5e-3);
+    assert_eq!(adc.config().current_source, CurrentSource::I500uA);
+    assert_eq!(adc.config().current_route_2, CurrentRoute::Ain3);
+    assert_eq!(adc.read_config().unwrap(), *adc.config());
+    assert_eq!(sim.mux(), Ok(Mux::Ain1Ain0));
+
+    sim.set_result(0x7F_FFFF);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim);
+    assert_eq!(rtd.measure(&mut adc), Err(Error::OpenInput));
+    sim.set_result(0);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim);
+    assert_eq!(rtd.measure(&mut adc), Err(Error::ShortedInput));
+}
+
+fn thermocouple_conversion(sim: &Simulator) -> i32 {
+    if sim.register(1) & 0x01 != 0 {
+        // cold junction at 25 °C
+        800 << 10
+    } else {
+        // type K from 25 °C to 500 °C, 20.644 mV - 1.000 mV at ±64 mV full scale
+        let raw = (20.644 - 1.000) / 64.0 * (1 << 23) as f64;
+        raw.round() as i32
+    }
+}
+
+#[test]
+fn thermocouple_measure() {
+    let thermocouple = Thermocouple {
+        kind: ThermocoupleType::K,
+        mux: Mux::Ain0Ain1,
+        gain: Gain::Gain32,
+    };
+    let mut sim = Simulator::new_i2c(ADDRESS);
+    sim.set_conversion(thermocouple_conversion);
+    let mut adc = ADS122x04::new_i2c(ADDRESS, &mut sim);
+    let measurement = thermocouple.measure(&mut adc).unwrap();
+    assert_eq!(measurement.cold_junction, 25.0);
+    assert!((measurement.emf - 19.644).abs() < 1e-3);
+    assert!((measurement.temperature - 500.0).abs() < 0.05);
+    assert!(!adc.config().temperature_sensor_mode);
+    assert_eq!(adc.read_config().unwrap(), *adc.config());
+    assert_eq!(sim.mux(), Ok(Mux::Ain0Ain1));
+}